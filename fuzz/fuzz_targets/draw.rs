@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// arbitrary data (including values the parser would never produce) through Frame and Canvas
+fuzz_target!(|input: (Vec<f64>, Vec<Vec<f64>>, u8, u8, bool)| {
+    let (xs, ys, width, height, count) = input;
+    dings::fuzz::draw_data(xs, ys, width, height, count);
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// arbitrary bytes on stdin, with any combination of the flags that affect parsing
+fuzz_target!(|input: ([bool; 6], &[u8])| {
+    let ([x_is_row, reduce, strict, thousands, decimal_comma, units], bytes) = input;
+    dings::fuzz::parse_and_draw(
+        bytes,
+        x_is_row,
+        reduce,
+        strict,
+        thousands,
+        decimal_comma,
+        units,
+    );
+});
@@ -0,0 +1,203 @@
+use crate::canvas::Cell;
+use crate::data::{BAND_MARK, HLINE_MARK, VLINE_MARK};
+use crate::{Canvas, Frame};
+
+/// A reference line or band drawn along with the data, like an SLO threshold or an event.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Annotation {
+    /// A horizontal line at the given Y.
+    HLine { y: f64, label: Option<String> },
+    /// A vertical line at the given X.
+    VLine { x: f64, label: Option<String> },
+    /// A shaded band across all the Y values between `from` and `to`.
+    Band {
+        from: f64,
+        to: f64,
+        label: Option<String>,
+    },
+}
+
+/// Splits `VALUE[:LABEL]` into its parts.
+fn value_and_label(s: &str) -> eyre::Result<(f64, Option<String>)> {
+    let (value, label) = match s.split_once(':') {
+        Some((value, label)) => (value, Some(label.to_string())),
+        None => (s, None),
+    };
+    let value: f64 = value
+        .parse()
+        .map_err(|e| eyre::eyre!("invalid value '{value}': {e}"))?;
+    eyre::ensure!(value.is_finite(), "value must be finite, got {value}");
+    eyre::ensure!(
+        label.as_deref().is_none_or(|l| l.is_ascii()),
+        "labels can only hold ASCII characters"
+    );
+    Ok((value, label))
+}
+
+/// The X and Y values the frame needs to include for all the `annotations` to be seen.
+pub(crate) fn extent(annotations: &[Annotation]) -> (Vec<f64>, Vec<f64>) {
+    let (mut xs, mut ys) = (Vec::new(), Vec::new());
+    for annotation in annotations {
+        match *annotation {
+            Annotation::HLine { y, .. } => ys.push(y),
+            Annotation::VLine { x, .. } => xs.push(x),
+            Annotation::Band { from, to, .. } => ys.extend([from, to]),
+        }
+    }
+    (xs, ys)
+}
+
+impl Annotation {
+    /// Parses the argument to `--hline`, `Y[:LABEL]`.
+    pub(crate) fn hline(s: &str) -> eyre::Result<Self> {
+        let (y, label) = value_and_label(s)?;
+        Ok(Annotation::HLine { y, label })
+    }
+
+    /// Parses the argument to `--vline`, `X[:LABEL]`.
+    pub(crate) fn vline(s: &str) -> eyre::Result<Self> {
+        let (x, label) = value_and_label(s)?;
+        Ok(Annotation::VLine { x, label })
+    }
+
+    /// Parses the argument to `--band`, `YMIN:YMAX[:LABEL]`.
+    pub(crate) fn band(s: &str) -> eyre::Result<Self> {
+        let Some((from, rest)) = s.split_once(':') else {
+            eyre::bail!("expected YMIN:YMAX (eg, 100:250), got '{s}'");
+        };
+        let from: f64 = from
+            .parse()
+            .map_err(|e| eyre::eyre!("invalid value '{from}': {e}"))?;
+        let (to, label) = value_and_label(rest)?;
+        eyre::ensure!(
+            from.is_finite() && from <= to,
+            "band must go from a lower to a higher Y, not {from} to {to}"
+        );
+        Ok(Annotation::Band { from, to, label })
+    }
+
+    /// Shades the cells of a band. This goes underneath everything else, including the axes.
+    pub(crate) fn shade_into(&self, canvas: &mut Canvas, using: &Frame) {
+        let Annotation::Band { from, to, .. } = *self else {
+            return;
+        };
+        let (Some(bottom), Some(top)) = (using.row_of(from), using.row_of(to)) else {
+            return;
+        };
+        for row in top..=bottom {
+            for column in 0..canvas.columns() {
+                if let Some(cell) = canvas.cell(row, column) {
+                    *cell = Cell::Glyph(BAND_MARK);
+                }
+            }
+        }
+    }
+
+    /// Draws a reference line. This goes on top of the axes, but underneath the data.
+    pub(crate) fn draw_into(&self, canvas: &mut Canvas, using: &Frame) {
+        match *self {
+            Annotation::HLine { y, .. } => {
+                let Some(row) = using.row_of(y) else {
+                    return;
+                };
+                for column in 0..canvas.columns() {
+                    if let Some(cell) = canvas.cell(row, column) {
+                        *cell = Cell::Glyph(HLINE_MARK);
+                    }
+                }
+            }
+            Annotation::VLine { x, .. } => {
+                let Some(column) = using.column_of(x) else {
+                    return;
+                };
+                for row in 0..canvas.rows().count() {
+                    if let Some(cell) = canvas.cell(row, column) {
+                        *cell = Cell::Glyph(VLINE_MARK);
+                    }
+                }
+            }
+            Annotation::Band { .. } => {}
+        }
+    }
+
+    /// Writes the label (if any) inside the canvas, next to the line or band.
+    ///
+    /// Labels go on top of everything, including the data, so that they can always be read (and
+    /// so that counting in [`Mode::Count`](crate::Mode::Count) never sees them).
+    pub(crate) fn label_into(&self, canvas: &mut Canvas, using: &Frame) {
+        let width = canvas.columns();
+        match self {
+            Annotation::HLine {
+                y,
+                label: Some(label),
+            } => {
+                // right-aligned on the line itself
+                if let Some(row) = using.row_of(*y) {
+                    let text = format!(" {label} ");
+                    canvas.write_str(row, width.saturating_sub(text.len()), &text);
+                }
+            }
+            Annotation::Band {
+                to,
+                label: Some(label),
+                ..
+            } => {
+                // right-aligned along the top edge of the band
+                if let Some(row) = using.row_of(*to) {
+                    let text = format!(" {label} ");
+                    canvas.write_str(row, width.saturating_sub(text.len()), &text);
+                }
+            }
+            Annotation::VLine {
+                x,
+                label: Some(label),
+            } => {
+                // along the top, to the right of the line if it fits, and to its left if not
+                if let Some(column) = using.column_of(*x) {
+                    let text = format!(" {label} ");
+                    let column = if column + 1 + text.len() <= width {
+                        column + 1
+                    } else {
+                        column.saturating_sub(text.len())
+                    };
+                    canvas.write_str(0, column, &text);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Annotation::hline("250:SLO").unwrap(),
+            Annotation::HLine {
+                y: 250.,
+                label: Some("SLO".to_string())
+            }
+        );
+        assert_eq!(
+            Annotation::vline("-3.5").unwrap(),
+            Annotation::VLine {
+                x: -3.5,
+                label: None
+            }
+        );
+        assert_eq!(
+            Annotation::band("100:250:ok").unwrap(),
+            Annotation::Band {
+                from: 100.,
+                to: 250.,
+                label: Some("ok".to_string())
+            }
+        );
+        assert!(Annotation::hline("high").is_err());
+        assert!(Annotation::band("100").is_err());
+        assert!(Annotation::band("250:100").is_err());
+    }
+}
@@ -1,100 +1,618 @@
-use crate::canvas::Mode;
+use crate::annotate::Annotation;
+use crate::canvas::{Charset, Mode};
+use crate::config::Config;
+use crate::emit::Emit;
+use crate::fit::Fit;
+use crate::follow::Window;
+use crate::frame::PAD;
+use crate::grid::Grid;
+use crate::outliers::Outliers;
+use crate::output::Output;
+use crate::parse::{Format, ParseOptions};
+use crate::scale::Scale;
+use crate::term;
+use crate::transform::{Delta, Quantiles, Smooth};
 use eyre::{Context, Ok};
 use lexopt::prelude::*;
+use std::ffi::OsString;
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub(crate) struct Opt {
-    pub(crate) log_x: bool,
-    pub(crate) log_y: bool,
+    pub(crate) x_scale: Scale,
+    pub(crate) y_scale: Scale,
     pub(crate) x_is_row: bool,
-    pub(crate) width: usize,
-    pub(crate) height: usize,
+    /// The width given with `-d`, if any (see [`Opt::size`]).
+    pub(crate) width: Option<usize>,
+    /// The height given with `-d`, if any.
+    pub(crate) height: Option<usize>,
     pub(crate) mode: Mode,
+    /// The characters to draw the plot with.
+    pub(crate) charset: Charset,
     pub(crate) cdf: bool,
+    /// Whether the CDF (if any) is the complementary one.
+    pub(crate) ccdf: bool,
     pub(crate) draw_axes: bool,
+    pub(crate) grid: Option<Grid>,
+    pub(crate) independent_axes: bool,
+    pub(crate) smooth: Option<Smooth>,
+    pub(crate) show_raw: bool,
+    pub(crate) delta: Option<Delta>,
+    pub(crate) reduce: bool,
+    pub(crate) parse: ParseOptions,
+    pub(crate) quantiles: Option<Quantiles>,
+    pub(crate) percentile_plot: bool,
+    pub(crate) format: Format,
+    pub(crate) fit: Option<Fit>,
+    /// Reference lines and bands to draw along with the data, in the order they were given.
+    pub(crate) annotations: Vec<Annotation>,
+    pub(crate) outliers: Option<Outliers>,
+    /// How many of the most extreme outliers to list below the plot.
+    pub(crate) top_outliers: usize,
+    /// Whether to open the plot in a terminal UI to explore it, rather than print it.
+    pub(crate) interactive: bool,
+    /// A file to keep reading (and plotting) as it grows, rather than reading stdin once.
+    pub(crate) follow: Option<PathBuf>,
+    /// How much of the followed file to keep.
+    pub(crate) window: Option<Window>,
+    /// A format to write the points and bounds of the plot in, instead of drawing it.
+    pub(crate) emit: Option<Emit>,
+    /// What to write the plot out as.
+    pub(crate) output: Output,
 }
 
 impl Opt {
     pub fn parse_from_env() -> eyre::Result<Option<Self>> {
         let mut opt = Opt {
-            log_x: false,
-            log_y: false,
+            x_scale: Scale::Linear,
+            y_scale: Scale::Linear,
             x_is_row: true,
-            width: 72,
-            height: 40,
+            width: None,
+            height: None,
             mode: Mode::Dot,
+            charset: Charset::Ascii,
             cdf: false,
+            ccdf: false,
             draw_axes: true,
+            grid: None,
+            independent_axes: false,
+            smooth: None,
+            show_raw: false,
+            delta: None,
+            reduce: false,
+            parse: ParseOptions::default(),
+            quantiles: None,
+            percentile_plot: false,
+            format: Format::Text,
+            fit: None,
+            annotations: Vec::new(),
+            outliers: None,
+            top_outliers: 0,
+            interactive: false,
+            follow: None,
+            window: None,
+            emit: None,
+            output: Output::Text,
         };
-        let mut parser = lexopt::Parser::from_env();
+        let mut bucket = None;
+        let config = Config::load().context("load config file")?;
+        let layers = config.layers(std::env::args_os().skip(1))?;
+        let command_line = layers.len() - 1;
+        // the size from the config file, which only applies where the command line doesn't say
+        let mut configured = (None, None);
+        for (layer, (source, args)) in layers.into_iter().enumerate() {
+            if layer == command_line {
+                configured = (opt.width.take(), opt.height.take());
+            }
+            let go_on = opt
+                .apply_args(args, &mut bucket)
+                .with_context(|| format!("parse {source}"))?;
+            if !go_on {
+                // help text was displayed
+                return Ok(None);
+            }
+        }
+        // --interactive fills the terminal whatever size the config file asks for
+        if !opt.interactive {
+            opt.width = opt.width.or(configured.0);
+            opt.height = opt.height.or(configured.1);
+        }
+
+        if opt.cdf {
+            eyre::ensure!(
+                opt.x_is_row,
+                "CDF is only over the Y value; an explicit X value will be ignored"
+            );
+            eyre::ensure!(
+            opt.x_scale.is_linear() || opt.ccdf,
+            "CDF is only over the Y value and changes the axes; logarithmic X would have no effet"
+        );
+            // NOTE: for --cdf, log y is interpreted as log of the _input_ not _output_, whereas
+            // --ccdf applies --log to the axes as drawn
+        }
+
+        if opt.interactive {
+            eyre::ensure!(
+                opt.width.is_none() && opt.height.is_none(),
+                "--interactive always fills the terminal, so -d does not apply"
+            );
+            eyre::ensure!(
+                opt.grid.is_none() && opt.fit.is_none() && opt.outliers.is_none(),
+                "--interactive does not (yet) support --grid, --facet, --fit, or --outliers"
+            );
+        }
+
+        if opt.emit.is_some() {
+            eyre::ensure!(
+                !opt.interactive
+                    && opt.grid.is_none()
+                    && opt.fit.is_none()
+                    && opt.outliers.is_none(),
+                "--emit writes the points of a single plot, \
+                 so --interactive, --grid, --facet, --fit, and --outliers do not apply"
+            );
+        }
+
+        if opt.output != Output::Text {
+            eyre::ensure!(
+                !opt.interactive
+                    && opt.emit.is_none()
+                    && opt.grid.is_none()
+                    && opt.fit.is_none()
+                    && opt.outliers.is_none(),
+                "--output charts the points of a single plot, \
+                 so --interactive, --emit, --grid, --facet, --fit, and --outliers do not apply"
+            );
+        }
+        if matches!(opt.output, Output::Png | Output::Inline(_)) {
+            eyre::ensure!(
+                matches!(opt.mode, Mode::Dot),
+                "-m count counts the points in each cell of a text plot, so it does not apply to \
+                 --output png, sixel, or kitty"
+            );
+        }
+
+        if opt.follow.is_some() {
+            eyre::ensure!(
+                !opt.interactive
+                    && !opt.reduce
+                    && opt.format == Format::Text
+                    && opt.output == Output::Text,
+                "--follow re-draws the plot as (text) input comes in, \
+                 so --interactive, --reduce, --format, and --output do not apply"
+            );
+        } else {
+            eyre::ensure!(opt.window.is_none(), "--window only applies to --follow");
+        }
+
+        eyre::ensure!(
+            opt.width.is_none_or(|width| width > PAD)
+                && opt.height.is_none_or(|height| height > PAD),
+            "-d must be larger than {PAD}x{PAD}"
+        );
+
+        eyre::ensure!(
+            !opt.show_raw || opt.smooth.is_some(),
+            "--show-raw only applies to --smooth"
+        );
+        eyre::ensure!(
+            !opt.show_raw || !opt.cdf,
+            "CDF replaces the raw points, so --show-raw would have nothing to show"
+        );
+        if let Some(quantiles) = &mut opt.quantiles {
+            quantiles.bucket = bucket;
+            eyre::ensure!(
+                !opt.cdf,
+                "--quantiles and --cdf are two different views of the distribution; pick one"
+            );
+        } else {
+            eyre::ensure!(bucket.is_none(), "--bucket only applies to --quantiles");
+        }
+
+        if opt.format == Format::Hlog {
+            eyre::ensure!(
+                opt.x_is_row && !opt.cdf && !opt.reduce && opt.delta.is_none(),
+                "an interval log is plotted either as a percentile distribution \
+                 or (with --quantiles) over time, so -x, --cdf, --reduce, \
+                 --rate, --diff, and --cumsum do not apply"
+            );
+            eyre::ensure!(
+                bucket.is_none(),
+                "each interval of an interval log is already its own bucket"
+            );
+            // without quantiles to plot over time, the best view is of the whole distribution
+            if opt.quantiles.is_none() {
+                opt.percentile_plot = true;
+            }
+        }
+
+        if opt.percentile_plot {
+            eyre::ensure!(
+                opt.x_is_row,
+                "--percentile-plot is only over the Y value; an explicit X value will be ignored"
+            );
+            eyre::ensure!(
+                opt.x_scale.is_linear(),
+                "--percentile-plot already uses a logarithmic percentile scale along X"
+            );
+            eyre::ensure!(
+                !opt.cdf && opt.quantiles.is_none(),
+                "--percentile-plot, --quantiles, and --cdf are different views of the \
+                 distribution; pick one"
+            );
+        }
+
+        if opt.reduce {
+            eyre::ensure!(
+                !opt.cdf
+                    && !opt.percentile_plot
+                    && opt.smooth.is_none()
+                    && opt.delta.is_none()
+                    && opt.quantiles.is_none()
+                    && opt.fit.is_none()
+                    && opt.outliers.is_none(),
+                "--reduce only keeps the extremes of each column, \
+                 so it cannot be combined with transformations that need every point"
+            );
+            eyre::ensure!(
+                !opt.interactive,
+                "--reduce keeps the extremes as drawn on the scales it's given, \
+                 so --interactive (which can switch scales) does not apply"
+            );
+        }
+        eyre::ensure!(
+            opt.fit.is_none() || (!opt.cdf && !opt.percentile_plot),
+            "--fit finds trends over X, which --cdf and --percentile-plot replace"
+        );
+        eyre::ensure!(
+            opt.outliers.is_none() || (!opt.cdf && !opt.percentile_plot && opt.quantiles.is_none()),
+            "--outliers points out rows of the input, which --cdf, --percentile-plot, \
+             and --quantiles replace"
+        );
+        eyre::ensure!(
+            opt.top_outliers == 0 || opt.outliers.is_some(),
+            "--top-outliers only applies to --outliers"
+        );
+        eyre::ensure!(
+            !opt.independent_axes || opt.grid.is_some(),
+            "--independent-axes only applies to --grid and --facet"
+        );
+
+        Ok(Some(opt))
+    }
+
+    /// The (width, height) of the plot.
+    ///
+    /// Any dimension not given with `-d` is sized to fit the terminal as it is right now (or for
+    /// an image, to a size that fits most places one is shown), so this is asked again each time
+    /// the plot is drawn.
+    pub(crate) fn size(&self) -> (usize, usize) {
+        let (width, height) = match (self.width, self.height) {
+            (Some(width), Some(height)) => return (width, height),
+            _ if self.output.is_image() => (800, 450),
+            _ => term::canvas_size(),
+        };
+        (self.width.unwrap_or(width), self.height.unwrap_or(height))
+    }
+
+    /// Sets the fields given by the flags in `args`, over whatever earlier layers of arguments
+    /// (see [`Config::layers`]) set them to.
+    ///
+    /// Returns `false` if the help text was displayed, and so nothing should be plotted.
+    fn apply_args(&mut self, args: Vec<OsString>, bucket: &mut Option<f64>) -> eyre::Result<bool> {
+        let mut parser = lexopt::Parser::from_args(args);
+        // a layer can't give more than one of --rate, --diff, and --cumsum, but it can replace the
+        // one given by an earlier layer
+        let mut delta_given = false;
         while let Some(arg) = parser.next().context("read next argument")? {
             match arg {
                 Short('h') | Long("help") => {
                     cli_help();
-                    return Ok(None);
+                    return Ok(false);
                 }
                 Short('d') => {
                     let dim = parser.value().context("value for -d")?;
                     let Some(dim) = dim.to_str() else {
                         eyre::bail!("-d argument contains invalid characters");
                     };
+                    // either side may be left out (eg, 120x or x30) to keep the default for it
                     if let Some((width, height)) = dim.split_once('x') {
-                        opt.width = width.parse().context("parse width in -d argument")?;
-                        opt.height = height.parse().context("parse height in -d argument")?;
+                        if !width.is_empty() {
+                            self.width = Some(width.parse().context("parse width in -d argument")?);
+                        }
+                        if !height.is_empty() {
+                            self.height =
+                                Some(height.parse().context("parse height in -d argument")?);
+                        }
                     } else {
                         eyre::bail!(
-                            "-d must be specified as WxH (eg, 72x40, which is the default)"
+                            "-d must be specified as WxH, Wx, or xH (eg, 72x40); \
+                             the default is the size of the terminal"
                         );
                     }
                 }
                 Short('l') | Long("log") => {
                     let dim = parser.value().context("value for --log")?;
                     if dim == "x" {
-                        opt.log_x = true;
+                        self.x_scale = Scale::Log(10.);
                     } else if dim == "y" {
-                        opt.log_y = true;
+                        self.y_scale = Scale::Log(10.);
                     } else if dim == "c" {
                         eyre::bail!("--log c is not yet supported");
                     } else {
                         eyre::bail!("--log takes x, y, or c");
                     }
                 }
+                Long(flag @ ("x-scale" | "y-scale")) => {
+                    let flag = flag.to_owned();
+                    let scale = parser
+                        .value()
+                        .with_context(|| format!("value for --{flag}"))?;
+                    let Some(scale) = scale.to_str() else {
+                        eyre::bail!("--{flag} argument contains invalid characters");
+                    };
+                    let scale = scale
+                        .parse()
+                        .with_context(|| format!("parse --{flag} argument"))?;
+                    if flag == "x-scale" {
+                        self.x_scale = scale;
+                    } else {
+                        self.y_scale = scale;
+                    }
+                }
                 Short('m') | Long("mode") => {
                     let mode = parser.value().context("value for --mode")?;
                     if mode == "dot" {
-                        opt.mode = Mode::Dot;
+                        self.mode = Mode::Dot;
                     } else if mode == "count" {
-                        opt.mode = Mode::Count;
+                        self.mode = Mode::Count;
                     } else {
                         eyre::bail!("--mode takes dot (the default) or count");
                     }
                 }
+                Long("charset") => {
+                    let charset = parser.value().context("value for --charset")?;
+                    let Some(charset) = charset.to_str() else {
+                        eyre::bail!("--charset argument contains invalid characters");
+                    };
+                    self.charset = charset.parse().context("parse --charset argument")?;
+                }
                 Short('x') => {
-                    opt.x_is_row = false;
+                    self.x_is_row = false;
                 }
                 Long("cdf") => {
-                    opt.cdf = true;
+                    self.cdf = true;
+                }
+                Long("ccdf") => {
+                    self.cdf = true;
+                    self.ccdf = true;
                 }
                 Short('A') => {
-                    opt.draw_axes = false;
+                    self.draw_axes = false;
+                }
+                Long("grid") => {
+                    let shape = parser.value().context("value for --grid")?;
+                    let Some(shape) = shape.to_str() else {
+                        eyre::bail!("--grid argument contains invalid characters");
+                    };
+                    let Some((rows, columns)) = shape.split_once('x') else {
+                        eyre::bail!("--grid must be specified as RxC (eg, 2x3)");
+                    };
+                    let rows = rows.parse().context("parse rows in --grid argument")?;
+                    let columns = columns
+                        .parse()
+                        .context("parse columns in --grid argument")?;
+                    eyre::ensure!(
+                        rows > 0 && columns > 0,
+                        "--grid must have at least one row and one column"
+                    );
+                    self.grid = Some(Grid::Fixed { rows, columns });
+                }
+                Long("facet") => {
+                    self.grid = Some(Grid::Auto);
+                }
+                Long("independent-axes") => {
+                    self.independent_axes = true;
+                }
+                Long("smooth") => {
+                    let smooth = parser.value().context("value for --smooth")?;
+                    let Some(smooth) = smooth.to_str() else {
+                        eyre::bail!("--smooth argument contains invalid characters");
+                    };
+                    self.smooth = Some(smooth.parse().context("parse --smooth argument")?);
+                }
+                Long("fit") => {
+                    let fit = parser.value().context("value for --fit")?;
+                    let Some(fit) = fit.to_str() else {
+                        eyre::bail!("--fit argument contains invalid characters");
+                    };
+                    self.fit = Some(fit.parse().context("parse --fit argument")?);
+                }
+                Long(flag @ ("hline" | "vline" | "band")) => {
+                    let flag = flag.to_owned();
+                    let value = parser
+                        .value()
+                        .with_context(|| format!("value for --{flag}"))?;
+                    let Some(value) = value.to_str() else {
+                        eyre::bail!("--{flag} argument contains invalid characters");
+                    };
+                    let annotation = match &*flag {
+                        "hline" => Annotation::hline(value),
+                        "vline" => Annotation::vline(value),
+                        _ => Annotation::band(value),
+                    };
+                    self.annotations
+                        .push(annotation.with_context(|| format!("parse --{flag} argument"))?);
+                }
+                Long("outliers") => {
+                    let outliers = parser.value().context("value for --outliers")?;
+                    let Some(outliers) = outliers.to_str() else {
+                        eyre::bail!("--outliers argument contains invalid characters");
+                    };
+                    self.outliers = Some(outliers.parse().context("parse --outliers argument")?);
+                }
+                Long("top-outliers") => {
+                    self.top_outliers = parser
+                        .value()
+                        .context("value for --top-outliers")?
+                        .parse()
+                        .context("parse --top-outliers argument")?;
+                }
+                Long("interactive") => {
+                    self.interactive = true;
+                }
+                Long("emit") => {
+                    let emit = parser.value().context("value for --emit")?;
+                    let Some(emit) = emit.to_str() else {
+                        eyre::bail!("--emit argument contains invalid characters");
+                    };
+                    self.emit = Some(emit.parse().context("parse --emit argument")?);
+                }
+                Long("output") => {
+                    let output = parser.value().context("value for --output")?;
+                    let Some(output) = output.to_str() else {
+                        eyre::bail!("--output argument contains invalid characters");
+                    };
+                    self.output = output.parse().context("parse --output argument")?;
+                }
+                Long("follow") => {
+                    self.follow = Some(parser.value().context("value for --follow")?.into());
+                }
+                Long("window") => {
+                    let window = parser.value().context("value for --window")?;
+                    let Some(window) = window.to_str() else {
+                        eyre::bail!("--window argument contains invalid characters");
+                    };
+                    self.window = Some(window.parse().context("parse --window argument")?);
+                }
+                Long("show-raw") => {
+                    self.show_raw = true;
+                }
+                Long(flag @ ("rate" | "diff" | "cumsum")) => {
+                    eyre::ensure!(
+                        !delta_given,
+                        "only one of --rate, --diff, and --cumsum can be given"
+                    );
+                    delta_given = true;
+                    self.delta = Some(match flag {
+                        "rate" => Delta::Rate,
+                        "diff" => Delta::Diff,
+                        _ => Delta::CumSum,
+                    });
+                }
+                Long("reduce") => {
+                    self.reduce = true;
+                }
+                Long("strict") => {
+                    self.parse.strict = true;
+                }
+                Long("thousands") => {
+                    self.parse.thousands = true;
+                }
+                Long("decimal-comma") => {
+                    self.parse.decimal_comma = true;
+                }
+                Long("units") => {
+                    self.parse.units = true;
+                }
+                Long("quantiles") => {
+                    let list = parser.value().context("value for --quantiles")?;
+                    let Some(list) = list.to_str() else {
+                        eyre::bail!("--quantiles argument contains invalid characters");
+                    };
+                    let percentiles = list
+                        .split(',')
+                        .map(|p| {
+                            let p: f64 = p
+                                .trim()
+                                .parse()
+                                .with_context(|| format!("parse quantile '{p}'"))?;
+                            eyre::ensure!(
+                                (0. ..=100.).contains(&p),
+                                "quantiles are percentiles, so must be in [0, 100], not {p}"
+                            );
+                            Ok(p)
+                        })
+                        .collect::<eyre::Result<_>>()
+                        .context("parse --quantiles argument")?;
+                    self.quantiles = Some(Quantiles {
+                        percentiles,
+                        bucket: None,
+                    });
+                }
+                Long("bucket") => {
+                    let width: f64 = parser
+                        .value()
+                        .context("value for --bucket")?
+                        .parse()
+                        .context("parse --bucket argument")?;
+                    eyre::ensure!(
+                        width > 0. && width.is_finite(),
+                        "--bucket must be a positive number"
+                    );
+                    *bucket = Some(width);
+                }
+                Long("percentile-plot") => {
+                    self.percentile_plot = true;
+                }
+                Long("format") => {
+                    let format = parser.value().context("value for --format")?;
+                    if format == "text" {
+                        self.format = Format::Text;
+                    } else if format == "hlog" {
+                        self.format = Format::Hlog;
+                    } else {
+                        eyre::bail!("--format takes text (the default) or hlog");
+                    }
+                }
+                Long(flag) if flag.starts_with("no-") => {
+                    self.turn_off(&flag["no-".len()..])?;
                 }
                 arg => return Err(arg.unexpected().into()),
             }
         }
+        Ok(true)
+    }
 
-        if opt.cdf {
-            eyre::ensure!(
-                opt.x_is_row,
-                "CDF is only over the Y value; an explicit X value will be ignored"
-            );
-            eyre::ensure!(
-            !opt.log_x,
-            "CDF is only over the Y value and changes the axes; logarithmic X would have no effet"
-        );
-            // NOTE: log y is interpreted as log of the _input_ not _output_
+    /// Undoes the flag `name` that takes no value, as given by `--no-NAME`, so that the command
+    /// line can turn off what a default or preset turned on.
+    fn turn_off(&mut self, name: &str) -> eyre::Result<()> {
+        match name {
+            "A" => self.draw_axes = true,
+            "x" => self.x_is_row = true,
+            "cdf" | "ccdf" => {
+                self.cdf = false;
+                self.ccdf = false;
+            }
+            "facet" => {
+                if matches!(self.grid, Some(Grid::Auto)) {
+                    self.grid = None;
+                }
+            }
+            "independent-axes" => self.independent_axes = false,
+            "show-raw" => self.show_raw = false,
+            "interactive" => self.interactive = false,
+            "reduce" => self.reduce = false,
+            "percentile-plot" => self.percentile_plot = false,
+            "strict" => self.parse.strict = false,
+            "thousands" => self.parse.thousands = false,
+            "decimal-comma" => self.parse.decimal_comma = false,
+            "units" => self.parse.units = false,
+            "rate" | "diff" | "cumsum" => {
+                let delta = match name {
+                    "rate" => Delta::Rate,
+                    "diff" => Delta::Diff,
+                    _ => Delta::CumSum,
+                };
+                if self.delta == Some(delta) {
+                    self.delta = None;
+                }
+            }
+            _ => eyre::bail!(
+                "--no-{name} does not apply; only flags that take no value can be turned off"
+            ),
         }
-
-        Ok(Some(opt))
+        Ok(())
     }
 }
 
@@ -102,22 +620,166 @@ fn cli_help() {
     println!("Dings: a quick command-line data visualization tool.\n");
     println!(
         "Usage: dings [-A] [-d WxH] [-h|--help] [-l|--log XY]
-              [-m|--mode MODE] [--cdf] [-x] [FILE]\n"
+              [--x-scale SCALE] [--y-scale SCALE]
+              [-m|--mode MODE] [--charset ascii|unicode] [--cdf | --ccdf] [-x]
+              [--grid RxC | --facet] [--independent-axes]
+              [--smooth KIND:PARAM [--show-raw]] [--fit KIND]
+              [--outliers TEST [--top-outliers N]] [--interactive]
+              [--follow FILE [--window N|x:T]]
+              [--emit json|tsv | --output OUTPUT]
+              [--hline Y[:LABEL]] [--vline X[:LABEL]] [--band YMIN:YMAX[:LABEL]]
+              [--rate | --diff | --cumsum] [--reduce]
+              [--quantiles P,P,... [--bucket N]] [--percentile-plot]
+              [--format FORMAT]
+              [--strict] [--thousands] [--decimal-comma] [--units]
+              [--no-FLAG...] [@PRESET...] [FILE]\n"
     );
 
     let commands = [
         ("A", "don't draw axes"),
-        ("d", "set width & height (e.g. \"-d 640x480\")"),
+        (
+            "d",
+            "set width & height (e.g. \"-d 640x480\" or \"-d 120x\"). Default fits the terminal",
+        ),
         ("h|help", "print help message"),
         ("l|log", "any of 'x' or 'y' to log scale"),
+        (
+            "x-scale",
+            "'linear', 'log', 'log:BASE', or 'symlog' (log-like, but allows 0 and negatives)",
+        ),
+        ("y-scale", "like --x-scale, but for the Y axis"),
         ("m|mode", "'dot'or 'count'. Default 'dot'"),
+        (
+            "charset",
+            "'ascii' (default), or 'unicode' for box-drawing axes and, with -m dot, half blocks",
+        ),
         (
             "cdf",
             "cumulative distribution function, only for the y value. Not compatible with log & x",
         ),
+        (
+            "ccdf",
+            "like --cdf, but the share of values greater than X, with --log for both axes",
+        ),
         ("x", "treat first column as X for all following Y columns"),
+        (
+            "grid",
+            "plot each Y column in its own panel of an RxC grid (e.g. \"--grid 2x2\")",
+        ),
+        (
+            "facet",
+            "like --grid, but pick the number of rows and columns automatically",
+        ),
+        (
+            "independent-axes",
+            "give each --grid panel its own bounds rather than sharing them",
+        ),
+        (
+            "smooth",
+            "smooth each Y column with mean:N, median:N (over the last N), or ewma:ALPHA",
+        ),
+        (
+            "show-raw",
+            "with --smooth, also draw the unsmoothed points faintly underneath",
+        ),
+        (
+            "fit",
+            "draw a least-squares linear, exp, or poly:N fit of each Y column, with its equation",
+        ),
+        (
+            "outliers",
+            "mark points that are outliers within their Y column by 'iqr' or 'zscore:N' with '&'",
+        ),
+        (
+            "top-outliers",
+            "with --outliers, list the N most extreme ones, with their input line numbers",
+        ),
+        (
+            "interactive",
+            "explore the plot in the terminal: zoom, pan, and toggle series, modes, and scales",
+        ),
+        (
+            "follow",
+            "keep reading FILE as it grows (and is rotated), like tail -F, re-drawing as it does",
+        ),
+        (
+            "window",
+            "with --follow, keep the last N rows (default: plot width), or the last T of X (x:T)",
+        ),
+        (
+            "emit",
+            "write the points and bounds (and with -m count, each cell's count) instead of a plot",
+        ),
+        (
+            "output",
+            "'text' (default), 'html', 'png' (-d in pixels), or 'sixel'/'kitty' (else text)",
+        ),
+        (
+            "hline",
+            "draw a horizontal reference line at Y, optionally labelled (e.g. \"--hline 250:SLO\")",
+        ),
+        (
+            "vline",
+            "draw a vertical reference line at X, optionally labelled. May be repeated",
+        ),
+        (
+            "band",
+            "shade the Y values from YMIN to YMAX, optionally labelled (e.g. \"--band 100:250\")",
+        ),
+        (
+            "rate",
+            "plot the change in each Y column per unit of X. Decreases are treated as resets",
+        ),
+        (
+            "diff",
+            "plot the difference between successive values of each Y column",
+        ),
+        ("cumsum", "plot the running sum of each Y column"),
+        (
+            "reduce",
+            "keep only about one point per cell while reading, for very large inputs",
+        ),
+        (
+            "percentile-plot",
+            "plot the value at each percentile of each Y column, with 90%, 99%, ... evenly spaced",
+        ),
+        (
+            "format",
+            "'text' or 'hlog' (an HdrHistogram interval log). Default 'text'",
+        ),
+        (
+            "strict",
+            "fail on values that aren't numbers, rather than treating them as missing",
+        ),
+        (
+            "thousands",
+            "allow thousands separators in numbers (e.g. \"1,234.5\")",
+        ),
+        (
+            "decimal-comma",
+            "use , as the decimal separator (and . for --thousands)",
+        ),
+        (
+            "units",
+            "allow (and scale by) unit suffixes like 12ms, 3.4k, 1.2GiB, or 45%",
+        ),
+        (
+            "no-FLAG",
+            "turn off a flag that takes no value, like one set by a preset (e.g. \"--no-ccdf\")",
+        ),
     ];
     for (cmd, desc) in commands {
         println!("   {:<12} {}", cmd, desc);
     }
+    println!(
+        "\nDefaults for any of these (under [defaults]) and named presets to use as @NAME (under
+[presets.NAME]) can be given in ~/.config/dings/config.toml (or $DINGS_CONFIG), as in:
+
+    [presets.latency]
+    l = [\"x\", \"y\"]
+    ccdf = true
+
+A flag given as false there is turned off, as with --no-FLAG. Flags on the command line take
+precedence over presets, which take precedence over defaults."
+    );
 }
@@ -1,4 +1,6 @@
+use crate::data::MARKS;
 use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Default, Copy, Clone)]
 pub(crate) enum Mode {
@@ -7,21 +9,89 @@ pub(crate) enum Mode {
     Count,
 }
 
+/// The characters to draw with.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Charset {
+    /// Only ASCII, which shows up the same everywhere.
+    #[default]
+    Ascii,
+    /// Box-drawing characters for the axes, and (in [`Mode::Dot`]) half blocks for twice the
+    /// vertical resolution.
+    Unicode,
+}
+
+impl FromStr for Charset {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => Ok(Charset::Ascii),
+            "unicode" => Ok(Charset::Unicode),
+            _ => eyre::bail!("unknown charset '{s}'; expected ascii or unicode"),
+        }
+    }
+}
+
+/// What is drawn in a single cell of a [`Canvas`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Cell {
+    /// Anything but the points of the data, like part of an axis, a fitted curve, or a label (or
+    /// nothing at all, as `' '`).
+    ///
+    /// Points drawn over these replace them, as if the cell were empty.
+    Glyph(char),
+    /// `count` points of the data, the last of which was of Y column `column`.
+    Points { column: usize, count: usize },
+    /// Points of the data in the top and/or bottom half of the cell, which is drawn with a half
+    /// (or full) block.
+    Halves { top: bool, bottom: bool },
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell::Glyph(' ')
+    }
+}
+
+impl Cell {
+    /// Adds `n` points of Y column `column` to this cell.
+    pub(crate) fn add_points(&mut self, column: usize, n: usize) {
+        let count = match *self {
+            Cell::Points { count, .. } => count + n,
+            _ => n,
+        };
+        *self = Cell::Points { column, count };
+    }
+
+    /// Adds a point in the top (or else the bottom) half of this cell.
+    pub(crate) fn add_half(&mut self, in_top: bool) {
+        let (top, bottom) = match *self {
+            Cell::Halves { top, bottom } => (top, bottom),
+            _ => (false, false),
+        };
+        *self = Cell::Halves {
+            top: top || in_top,
+            bottom: bottom || !in_top,
+        };
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Canvas {
     stride: usize,
-    cells: Box<[u8]>,
+    cells: Box<[Cell]>,
     pub(crate) mode: Mode,
+    pub(crate) charset: Charset,
+    /// Whether any points were drawn with half blocks, and so have no mark to tell them apart.
+    pub(crate) halved: bool,
 }
 
 impl fmt::Display for Canvas {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for row in self.rows() {
-            write!(
-                f,
-                "{}",
-                std::str::from_utf8(row).expect("row is invalid utf-8")
-            )?;
+            for &cell in row {
+                write!(f, "{}", self.char_of(cell))?;
+            }
             writeln!(f)?;
         }
         Ok(())
@@ -29,19 +99,118 @@ impl fmt::Display for Canvas {
 }
 
 impl Canvas {
-    pub(crate) fn new(rows: usize, columns: usize, mode: Mode) -> Self {
+    pub(crate) fn new(rows: usize, columns: usize, mode: Mode, charset: Charset) -> Self {
         Self {
             stride: columns,
-            cells: vec![b' '; rows * columns].into_boxed_slice(),
+            cells: vec![Cell::default(); rows * columns].into_boxed_slice(),
             mode,
+            charset,
+            halved: false,
         }
     }
 
-    pub(crate) fn rows(&self) -> impl Iterator<Item = &[u8]> {
+    /// The number of cells in each row.
+    pub(crate) fn columns(&self) -> usize {
+        self.stride
+    }
+
+    pub(crate) fn rows(&self) -> impl Iterator<Item = &[Cell]> {
         self.cells.chunks_exact(self.stride)
     }
 
-    pub(crate) fn cell(&mut self, row: usize, column: usize) -> Option<&mut u8> {
+    pub(crate) fn cell(&mut self, row: usize, column: usize) -> Option<&mut Cell> {
+        if column >= self.stride {
+            return None;
+        }
         self.cells.get_mut(row * self.stride + column)
     }
+
+    /// Whether points can be drawn with half blocks (see [`Cell::Halves`]) on this canvas.
+    pub(crate) fn halves(&self) -> bool {
+        matches!(self.mode, Mode::Dot) && self.charset == Charset::Unicode
+    }
+
+    /// The mark the points of Y column `column` were drawn with.
+    pub(crate) fn mark(&self, column: usize) -> char {
+        if self.halved {
+            '█'
+        } else {
+            char::from(MARKS[column])
+        }
+    }
+
+    /// The character that shows `cell`.
+    fn char_of(&self, cell: Cell) -> char {
+        match (cell, self.mode) {
+            (Cell::Glyph(c), _) => c,
+            (Cell::Points { column, .. }, Mode::Dot) => char::from(MARKS[column]),
+            // in count mode, each cell shows how many points fall within it from _any_ Y column,
+            // in base 36 and saturating at '#'. a lone point keeps its mark, though, so that
+            // different Y columns can still be told apart where they don't overlap.
+            (Cell::Points { column, count: 1 }, Mode::Count) => char::from(MARKS[column]),
+            (Cell::Points { count, .. }, Mode::Count) => u32::try_from(count)
+                .ok()
+                .and_then(|count| char::from_digit(count, 36))
+                .unwrap_or('#'),
+            (Cell::Halves { top, bottom }, _) => match (top, bottom) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                _ => '▄',
+            },
+        }
+    }
+
+    /// Writes `text` starting at the given cell, cutting it off at the right edge.
+    pub(crate) fn write_str(&mut self, row: usize, column: usize, text: &str) {
+        for (i, c) in text.chars().enumerate() {
+            let Some(cell) = self.cell(row, column + i) else {
+                break;
+            };
+            *cell = Cell::Glyph(c);
+        }
+    }
+
+    /// Copies all of `other` into this canvas with its top-left corner at `(row, column)`.
+    ///
+    /// Whatever part of `other` does not fit is cut off.
+    pub(crate) fn place(&mut self, row: usize, column: usize, other: &Canvas) {
+        self.halved |= other.halved;
+        for (i, from) in other.rows().enumerate() {
+            for (j, &c) in from.iter().enumerate() {
+                let Some(cell) = self.cell(row + i, column + j) else {
+                    break;
+                };
+                *cell = c;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_in_base_36() {
+        let mut canvas = Canvas::new(1, 4, Mode::Count, Charset::Ascii);
+        canvas.write_str(0, 0, "+-");
+        for (column, points) in [1, 2, 10, 36].into_iter().enumerate() {
+            let cell = canvas.cell(0, column).unwrap();
+            for _ in 0..points {
+                cell.add_points(1, 1);
+            }
+        }
+        assert_eq!(canvas.to_string(), "*2a#\n");
+    }
+
+    #[test]
+    fn halves() {
+        let mut canvas = Canvas::new(1, 3, Mode::Dot, Charset::Unicode);
+        canvas.cell(0, 0).unwrap().add_half(true);
+        canvas.cell(0, 1).unwrap().add_half(false);
+        let both = canvas.cell(0, 2).unwrap();
+        both.add_half(true);
+        both.add_half(false);
+        assert_eq!(canvas.to_string(), "▀▄█\n");
+    }
 }
@@ -0,0 +1,212 @@
+use eyre::Context;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Defaults and named presets, read from `config.toml` in the `dings` config directory.
+///
+/// Both are written with the flags they stand for, by the name they're given on the command line
+/// (so `d` for `-d`, and `cdf` for `--cdf`). A flag that takes no value is given as `true` (or as
+/// `false` to turn it off, like `--no-cdf`), and one that can be repeated (like `-l`) may be given
+/// a list of values:
+///
+/// ```toml
+/// [defaults]
+/// d = "160x50"
+///
+/// [presets.latency]
+/// l = ["x", "y"]
+/// ccdf = true
+/// ```
+///
+/// The defaults apply to every plot, a preset applies when it is named on the command line (as
+/// `@latency`), and the command line itself goes last so that it takes precedence over both.
+#[derive(Debug, Default)]
+pub(crate) struct Config {
+    defaults: Vec<OsString>,
+    presets: BTreeMap<String, Vec<OsString>>,
+}
+
+/// Where the config file is, if anywhere.
+///
+/// `$DINGS_CONFIG` overrides the default location, and can be set to the empty string to not
+/// read any config file at all.
+fn path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("DINGS_CONFIG") {
+        return (!path.is_empty()).then(|| path.into());
+    }
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))?;
+    Some(dir.join("dings").join("config.toml"))
+}
+
+/// Turns a table of flags (as described on [`Config`]) into the arguments they stand for.
+fn to_args(table: &toml::Table) -> eyre::Result<Vec<OsString>> {
+    let mut args = Vec::new();
+    for (name, value) in table {
+        let flag = if name.len() == 1 {
+            format!("-{name}")
+        } else {
+            format!("--{name}")
+        };
+        let values = match value {
+            toml::Value::Array(values) => values.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        for value in values {
+            let value = match value {
+                toml::Value::Boolean(true) => None,
+                toml::Value::Boolean(false) => {
+                    args.push(OsString::from(format!("--no-{name}")));
+                    continue;
+                }
+                toml::Value::String(s) => Some(s.clone()),
+                toml::Value::Integer(n) => Some(n.to_string()),
+                toml::Value::Float(n) => Some(n.to_string()),
+                _ => eyre::bail!("{name} must be true, a string, a number, or a list of those"),
+            };
+            args.push(OsString::from(&flag));
+            args.extend(value.map(OsString::from));
+        }
+    }
+    Ok(args)
+}
+
+impl Config {
+    /// Reads the config file, or returns an empty config if there is none.
+    pub(crate) fn load() -> eyre::Result<Self> {
+        let Some(path) = path() else {
+            return Ok(Self::default());
+        };
+        let config = match std::fs::read_to_string(&path) {
+            Ok(config) => config,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+        };
+        config
+            .parse()
+            .with_context(|| format!("parse {}", path.display()))
+    }
+
+    /// Splits the command line `args` into layers of arguments to parse in order, each with a
+    /// description of where it came from: the defaults, then each `@preset` named in `args` (in
+    /// the order they're named), and then the rest of `args`.
+    pub(crate) fn layers(
+        &self,
+        args: impl IntoIterator<Item = OsString>,
+    ) -> eyre::Result<Vec<(String, Vec<OsString>)>> {
+        let mut layers = vec![("config defaults".to_string(), self.defaults.clone())];
+        let mut rest = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                // everything after this is positional, so can't name a preset
+                rest.push(arg);
+                rest.extend(args.by_ref());
+                break;
+            }
+            let Some(name) = arg.to_str().and_then(|arg| arg.strip_prefix('@')) else {
+                rest.push(arg);
+                continue;
+            };
+            let Some(preset) = self.presets.get(name) else {
+                let known: Vec<_> = self.presets.keys().map(|name| format!("@{name}")).collect();
+                eyre::bail!(
+                    "unknown preset @{name}; the config file has {}",
+                    if known.is_empty() {
+                        "none".to_string()
+                    } else {
+                        known.join(", ")
+                    }
+                );
+            };
+            layers.push((format!("preset @{name}"), preset.clone()));
+        }
+        layers.push(("command-line arguments".to_string(), rest));
+        Ok(layers)
+    }
+}
+
+impl std::str::FromStr for Config {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut table: toml::Table = s.parse()?;
+        let mut config = Config::default();
+        if let Some(defaults) = table.remove("defaults") {
+            let Some(defaults) = defaults.as_table() else {
+                eyre::bail!("defaults must be a table of flags");
+            };
+            config.defaults = to_args(defaults).context("read defaults")?;
+        }
+        if let Some(presets) = table.remove("presets") {
+            let Some(presets) = presets.as_table() else {
+                eyre::bail!("presets must be a table of presets");
+            };
+            for (name, preset) in presets {
+                let Some(preset) = preset.as_table() else {
+                    eyre::bail!("preset {name} must be a table of flags");
+                };
+                let args = to_args(preset).with_context(|| format!("read preset {name}"))?;
+                config.presets.insert(name.clone(), args);
+            }
+        }
+        if let Some(key) = table.keys().next() {
+            eyre::bail!("unknown key '{key}'; expected only defaults and presets");
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn layers() {
+        let config: Config = r#"
+            [defaults]
+            d = "160x50"
+
+            [presets.latency]
+            l = ["x", "y"]
+            ccdf = true
+            x = false
+            bucket = 10
+        "#
+        .parse()
+        .unwrap();
+
+        let layers = config
+            .layers(args(&["-x", "@latency", "-d", "80x", "--", "@file"]))
+            .unwrap();
+        let layers: Vec<_> = layers.into_iter().map(|(_, args)| args).collect();
+        assert_eq!(
+            layers,
+            [
+                args(&["-d", "160x50"]),
+                args(&["--bucket", "10", "--ccdf", "-l", "x", "-l", "y", "--no-x"]),
+                args(&["-x", "-d", "80x", "--", "@file"]),
+            ]
+        );
+
+        let e = config.layers(args(&["@nope"])).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "unknown preset @nope; the config file has @latency"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("[default]\nd = '1x1'".parse::<Config>().is_err());
+        assert!("[defaults]\nd = { w = 1 }".parse::<Config>().is_err());
+        assert!("presets = 1".parse::<Config>().is_err());
+    }
+}
@@ -1,71 +1,287 @@
-use crate::{Canvas, Frame, Mode};
+use crate::canvas::Cell;
+use crate::parse::{Row, Unit};
+use crate::reduce::Reducer;
+use crate::{Canvas, Frame};
+use eyre::Context;
 
 pub const MARKS: &[u8] = b"@*^!~%ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 
-#[derive(Debug, Default)]
+/// The color of each Y column in graphical output, wrapping around if there are more columns
+/// than colors.
+pub const COLORS: &[[u8; 3]] = &[
+    [0x1f, 0x77, 0xb4],
+    [0xff, 0x7f, 0x0e],
+    [0x2c, 0xa0, 0x2c],
+    [0xd6, 0x27, 0x28],
+    [0x94, 0x67, 0xbd],
+    [0x8c, 0x56, 0x4b],
+    [0xe3, 0x77, 0xc2],
+    [0x7f, 0x7f, 0x7f],
+    [0xbc, 0xbd, 0x22],
+    [0x17, 0xbe, 0xcf],
+];
+
+/// The mark used for points that should be visible, but not draw attention (like the raw points
+/// underneath a smoothed series).
+///
+/// Like every mark that isn't a point of the data, [`Mode::Count`](crate::Mode::Count) treats it
+/// as blank, so that counting the (non-faint) points drawn on top still works.
+pub const FAINT_MARK: char = '.';
+
+/// The mark used to draw curves fit to the data (see [`crate::fit`]).
+pub const FIT_MARK: char = ':';
+
+/// The marks used to draw reference lines and bands (see [`crate::annotate`]).
+pub const HLINE_MARK: char = '=';
+pub const VLINE_MARK: char = '\'';
+pub const BAND_MARK: char = '`';
+
+/// The mark used for points flagged as outliers (see [`crate::outliers`]).
+///
+/// This is neither one of [`MARKS`] nor a count, and is drawn on top of everything else, so it
+/// stands out in either mode (and [`Mode::Count`](crate::Mode::Count) never has to count it).
+pub const OUTLIER_MARK: char = '&';
+
+/// With a [`Reducer`], how many rows to read before folding them into the reduced summary.
+const REDUCE_EVERY: usize = 4096;
+
+#[derive(Debug, Default, Clone)]
 pub(crate) struct Data {
     pub(crate) xs: Vec<f64>,
     pub(crate) ys: Vec<Vec<f64>>,
+    pub(crate) x_unit: Unit,
+    pub(crate) y_unit: Unit,
+    /// The names of the Y columns. Columns past the end of this are named by their index.
+    pub(crate) labels: Vec<String>,
+    /// The (one-based) input line each row was read from.
+    ///
+    /// This is empty if the rows don't map back to lines of input (like after computing a CDF).
+    pub(crate) lines: Vec<usize>,
+    /// How many points of the input each row stands for, if it stands for more than one (see
+    /// [`Reducer`]).
+    ///
+    /// This is empty if every row is a single point of the input.
+    pub(crate) weights: Vec<usize>,
 }
 
 impl Data {
-    pub(crate) fn draw_into(&self, canvas: &mut Canvas, using: &Frame) {
-        for (row, x) in self.xs.iter().copied().enumerate() {
-            let x_cell = using.x_to_column(x);
-
-            for (column, ys) in self.ys.iter().enumerate() {
-                let y = ys[row];
+    /// Collects `rows` into a data set.
+    ///
+    /// If `x_is_row` is set, the X of each row is its (zero-based) index, and all its values are
+    /// Y values. Otherwise, the first value is the X value.
+    ///
+    /// If a `reducer` is given, rows are folded into it as they are read, and the returned data set
+    /// holds only the points it kept.
+    pub(crate) fn read_from(
+        rows: impl Iterator<Item = eyre::Result<Row>>,
+        x_is_row: bool,
+        mut reducer: Option<Reducer>,
+    ) -> eyre::Result<Self> {
+        let mut data = Data::default();
+        for (i, row) in rows.enumerate() {
+            let row = row.with_context(|| format!("read line {}", i + 1))?;
+            data.push_row(&row, x_is_row.then_some(i as f64), i + 1);
 
-                if !y.is_finite() {
-                    continue;
+            if let Some(reducer) = &mut reducer {
+                if data.xs.len() >= REDUCE_EVERY {
+                    reducer.absorb(&mut data);
                 }
+            }
+        }
+        if let Some(mut reducer) = reducer {
+            reducer.absorb(&mut data);
+            data = Data {
+                x_unit: data.x_unit,
+                y_unit: data.y_unit,
+                labels: data.labels,
+                ..reducer.into_data()
+            };
+        }
+        Ok(data)
+    }
 
-                const CMP_PAD: f64 = 0.001;
-                let (min_x, max_x) = using.x_bounds();
-                assert!(x >= min_x - CMP_PAD);
-                assert!(x <= max_x + CMP_PAD);
-
-                let y_cell = using.y_to_row(y);
-
-                let mode = canvas.mode;
-                let Some(cell) = canvas.cell(y_cell, x_cell) else {
-                    panic!("invalid cell ({y_cell}, {x_cell}) for data point ({x}, {y})");
-                };
-
-                match mode {
-                    Mode::Dot => *cell = MARKS[column],
-                    Mode::Count => {
-                        // in count mode, we want each cell to display the number of points that fall
-                        // within that cell from _any_ dataset. we can get this behaviour by abusing
-                        // the `u8` that gets stored for every cell. we simply use that `u8` as a
-                        // counter (well, counter in base36...) that saturates in '#'.
-                        *cell = match *cell {
-                            // NOTE: it's intentional that we _don't_ match 'z' here
-                            #[allow(clippy::almost_complete_range)]
-                            b'0'..b'9' | b'a'..b'z' => *cell + 1,
-                            b'9' => b'a',
-                            b'z' | b'#' => b'#',
-                            // this part is (extra) cursed.
-                            // something needs to initialize the u8 base36 counters (sorry not sorry),
-                            // because their previous value could be ' ' from the blank canvas, or '+',
-                            // '-', '.', or '|' from the axes. we _could_ do that with a loop before
-                            // this one that sets ever data point cell to '0', but doing so would mean
-                            // we also need to compute all the cell values multiple times (or cache
-                            // them somehow). instead, we simply assume that any non-base36-and-not-#
-                            // value is 0.
-                            //
-                            // _but_, we want non-overlapping values to keep their mark so different
-                            // datasets can be told apart, so 1 is MARKS[column]. this in turn requires
-                            // that none of the axis marks are in MARKS.
-                            b'-' | b'+' | b'.' | b'|' | b' ' => MARKS[column],
-                            c if MARKS.contains(&c) => b'2',
-                            _ => unreachable!(
-                                "cell at ({y_cell}, {x_cell}) held unexpected counting mark '{cell}'"
-                            ),
-                        }
+    /// Adds the values of `row` as a new row.
+    ///
+    /// `x` is the X value of the row if it is not to be taken from the row itself, and `line` is
+    /// the input line it came from.
+    pub(crate) fn push_row(&mut self, row: &Row, mut x: Option<f64>, line: usize) {
+        let mut column = 0;
+        for (i, &v) in row.values.iter().enumerate() {
+            let unit = row.units.get(i).and_then(Option::as_deref);
+            if x.is_some() {
+                if let Some(unit) = unit {
+                    self.y_unit.observe(unit);
+                }
+                // have a data point!
+                if column + 1 > self.ys.len() {
+                    if column >= MARKS.len() {
+                        // we can't label these ones!
+                        continue;
                     }
+                    assert_eq!(
+                        column,
+                        self.ys.len(),
+                        "we will only ever add a single new column at a time"
+                    );
+                    // discovered a new column!
+                    // need to add the column, which means adding empty
+                    // values for that column for all pre-existing rows.
+                    self.ys.push(vec![f64::NAN; self.xs.len()]);
+                }
+                self.ys[column].push(v);
+                column += 1;
+            } else {
+                // found x value
+                if let Some(unit) = unit {
+                    self.x_unit.observe(unit);
+                }
+                x = Some(v);
+            }
+        }
+
+        // whatever x value we discovered is the x for the row
+        // NOTE: if this is None, that means there were no column values at all, which is
+        // equivalent to an empty line, which we simply don't count as a sample. note also that
+        // this means ys has not been pushed to either.
+        if let Some(x) = x {
+            self.xs.push(x);
+            self.lines.push(line);
+        }
+
+        // make sure we fill in the other column values
+        for y in &mut self.ys {
+            if y.len() < self.xs.len() {
+                assert_eq!(y.len(), self.xs.len() - 1);
+                y.push(f64::NAN);
+            }
+        }
+    }
+
+    /// Removes the first `n` rows.
+    pub(crate) fn drop_first(&mut self, n: usize) {
+        let n = n.min(self.xs.len());
+        self.xs.drain(..n);
+        for ys in &mut self.ys {
+            ys.drain(..n);
+        }
+        self.lines.drain(..n.min(self.lines.len()));
+        self.weights.drain(..n.min(self.weights.len()));
+    }
+
+    /// Returns how many points of the input the given row stands for (see [`Data::weights`]).
+    pub(crate) fn weight(&self, row: usize) -> usize {
+        self.weights.get(row).copied().unwrap_or(1)
+    }
+
+    /// Returns the name of the given Y column for use in legends.
+    pub(crate) fn label(&self, column: usize) -> String {
+        self.labels
+            .get(column)
+            .cloned()
+            .unwrap_or_else(|| column.to_string())
+    }
+
+    /// Returns a copy of this data set that holds only the given Y column.
+    pub(crate) fn series(&self, column: usize) -> Data {
+        Data {
+            xs: self.xs.clone(),
+            ys: vec![self.ys[column].clone()],
+            x_unit: self.x_unit.clone(),
+            y_unit: self.y_unit.clone(),
+            labels: vec![self.label(column)],
+            lines: self.lines.clone(),
+            weights: self.weights.clone(),
+        }
+    }
+
+    pub(crate) fn draw_into(&self, canvas: &mut Canvas, using: &Frame) {
+        // half blocks can't tell Y columns apart, so they're only for a lone one
+        let halves = canvas.halves() && self.ys.len() == 1;
+        for column in 0..self.ys.len() {
+            self.draw_points_into(canvas, using, column, halves);
+        }
+    }
+
+    /// Draws every point of every Y column with [`FAINT_MARK`], regardless of mode.
+    pub(crate) fn draw_faint_into(&self, canvas: &mut Canvas, using: &Frame) {
+        for column in 0..self.ys.len() {
+            self.draw_faint_series_into(canvas, using, column);
+        }
+    }
+
+    /// Draws every point of the given Y column with [`FAINT_MARK`], regardless of mode.
+    pub(crate) fn draw_faint_series_into(&self, canvas: &mut Canvas, using: &Frame, column: usize) {
+        for (&x, &y) in self.xs.iter().zip(&self.ys[column]) {
+            if !x.is_finite() || !y.is_finite() {
+                continue;
+            }
+            let Some((y_cell, x_cell)) = using.point_to_cell((x, y)) else {
+                continue;
+            };
+            let Some(cell) = canvas.cell(y_cell, x_cell) else {
+                panic!("invalid cell ({y_cell}, {x_cell}) for data point ({x}, {y})");
+            };
+            *cell = Cell::Glyph(FAINT_MARK);
+        }
+    }
+
+    /// Draws only the given Y column, with the mark it would get in [`Data::draw_into`], or
+    /// with half blocks if the canvas uses those (since it's the only column drawn).
+    pub(crate) fn draw_series_into(&self, canvas: &mut Canvas, using: &Frame, column: usize) {
+        let halves = canvas.halves();
+        self.draw_points_into(canvas, using, column, halves);
+    }
+
+    fn draw_points_into(&self, canvas: &mut Canvas, using: &Frame, column: usize, halves: bool) {
+        canvas.halved |= halves;
+        for (row, (&x, &y)) in self.xs.iter().zip(&self.ys[column]).enumerate() {
+            // NOTE: X can be missing too if it was taken from the input
+            if !x.is_finite() || !y.is_finite() {
+                continue;
+            }
+
+            // NOTE: the point may also not be drawable on the frame's scales (like 0 on log)
+            let Some((y_cell, x_cell)) = using.point_to_cell((x, y)) else {
+                continue;
+            };
+
+            let Some(cell) = canvas.cell(y_cell, x_cell) else {
+                panic!("invalid cell ({y_cell}, {x_cell}) for data point ({x}, {y})");
+            };
+
+            // in count mode, the cell keeps count of the points that fall within it from _any_
+            // Y column.
+            if halves {
+                cell.add_half(using.in_top_half(y));
+            } else {
+                cell.add_points(column, self.weight(row));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::canvas::Charset;
+    use crate::frame::tests::finite_data;
+    use crate::{Canvas, Frame, Mode, PAD};
+    use quickcheck::{quickcheck, TestResult};
+
+    quickcheck! {
+        fn draw_into_does_not_panic(points: Vec<(f64, f64)>, width: u8, height: u8) -> TestResult {
+            let Some(data) = finite_data(points) else {
+                return TestResult::discard();
+            };
+            let (width, height) = (PAD + 1 + usize::from(width), PAD + 1 + usize::from(height));
+            let frame = Frame::new_over(width, height, &data);
+            for mode in [Mode::Dot, Mode::Count] {
+                for charset in [Charset::Ascii, Charset::Unicode] {
+                    let mut canvas = Canvas::new(height, width, mode, charset);
+                    frame.draw_into(&mut canvas);
+                    data.draw_into(&mut canvas, &frame);
                 }
             }
+            TestResult::passed()
         }
     }
 }
@@ -0,0 +1,212 @@
+use crate::parse::Unit;
+use crate::{Data, Frame, Mode, MARKS};
+use std::io::Write;
+use std::str::FromStr;
+
+/// A machine-readable format to write the points of a plot in, instead of drawing it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Emit {
+    /// One JSON object on a single line (so `--follow` produces JSON lines).
+    Json,
+    /// Tab-separated columns, with the bounds in `#` comment lines above them.
+    Tsv,
+}
+
+impl FromStr for Emit {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Emit::Json),
+            "tsv" => Ok(Emit::Tsv),
+            _ => eyre::bail!("unknown format '{s}'; expected json or tsv"),
+        }
+    }
+}
+
+/// The unit of an axis, if all the values that had one agree on it.
+fn unit_name(unit: &Unit) -> Option<&str> {
+    match unit {
+        Unit::Is(unit) => Some(unit),
+        Unit::None | Unit::Mixed => None,
+    }
+}
+
+/// Counts how many points of `data` land in each cell of `frame`, top row first.
+///
+/// This is what `-m count` draws, except that the counts don't saturate.
+fn counts(data: &Data, frame: &Frame) -> Vec<Vec<usize>> {
+    let (width, height) = frame.size();
+    let mut counts = vec![vec![0; width]; height];
+    for ys in &data.ys {
+        for (i, (&x, &y)) in data.xs.iter().zip(ys).enumerate() {
+            if !x.is_finite() || !y.is_finite() {
+                continue;
+            }
+            if let Some((row, column)) = frame.point_to_cell((x, y)) {
+                counts[row][column] += data.weight(i);
+            }
+        }
+    }
+    counts
+}
+
+/// Writes `s` as a JSON string.
+fn json_str(s: &str, out: &mut impl Write) -> std::io::Result<()> {
+    write!(out, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' => write!(out, "\\n")?,
+            '\t' => write!(out, "\\t")?,
+            c if c.is_control() => write!(out, "\\u{:04x}", u32::from(c))?,
+            c => write!(out, "{c}")?,
+        }
+    }
+    write!(out, "\"")
+}
+
+/// Writes `v` as a JSON number, or `null` if JSON can't represent it.
+fn json_num(v: f64, out: &mut impl Write) -> std::io::Result<()> {
+    if v.is_finite() {
+        write!(out, "{v}")
+    } else {
+        write!(out, "null")
+    }
+}
+
+impl Emit {
+    /// Writes the points of `data` in the units of the data (so as they were plotted, but before
+    /// any log scale is applied), the bounds and scale of each axis of `frame`, and with
+    /// [`Mode::Count`], how many points land in each cell.
+    pub(crate) fn write(
+        self,
+        data: &Data,
+        frame: &Frame,
+        mode: Mode,
+        mut out: impl Write,
+    ) -> eyre::Result<()> {
+        let axes = [
+            (
+                "x",
+                frame.x_scale(),
+                frame.x_bounds(),
+                &data.x_unit,
+                frame.dropped().0,
+            ),
+            (
+                "y",
+                frame.y_scale(),
+                frame.y_bounds(),
+                &data.y_unit,
+                frame.dropped().1,
+            ),
+        ];
+        let counts = matches!(mode, Mode::Count).then(|| counts(data, frame));
+
+        match self {
+            Emit::Json => {
+                write!(out, "{{")?;
+                for (name, scale, (min, max), unit, dropped) in axes {
+                    write!(out, "\"{name}\":{{\"scale\":\"{}\",\"min\":", scale.name())?;
+                    json_num(min, &mut out)?;
+                    write!(out, ",\"max\":")?;
+                    json_num(max, &mut out)?;
+                    write!(out, ",\"unit\":")?;
+                    match unit_name(unit) {
+                        Some(unit) => json_str(unit, &mut out)?,
+                        None => write!(out, "null")?,
+                    }
+                    write!(out, ",\"dropped\":{dropped}}},")?;
+                }
+                write!(out, "\"series\":[")?;
+                for (column, ys) in data.ys.iter().enumerate() {
+                    if column > 0 {
+                        write!(out, ",")?;
+                    }
+                    write!(out, "{{\"label\":")?;
+                    json_str(&data.label(column), &mut out)?;
+                    write!(
+                        out,
+                        ",\"mark\":\"{}\",\"points\":[",
+                        char::from(MARKS[column])
+                    )?;
+                    // missing values aren't points, so they're left out rather than made null
+                    let points = data.xs.iter().zip(ys);
+                    let points = points.filter(|(x, y)| x.is_finite() && y.is_finite());
+                    for (i, (x, y)) in points.enumerate() {
+                        write!(out, "{}[{x},{y}]", if i > 0 { "," } else { "" })?;
+                    }
+                    write!(out, "]}}")?;
+                }
+                write!(out, "]")?;
+                if let Some(counts) = counts {
+                    write!(out, ",\"counts\":[")?;
+                    for (i, row) in counts.iter().enumerate() {
+                        let row: Vec<_> = row.iter().map(usize::to_string).collect();
+                        write!(out, "{}[{}]", if i > 0 { "," } else { "" }, row.join(","))?;
+                    }
+                    write!(out, "]")?;
+                }
+                writeln!(out, "}}")?;
+            }
+            Emit::Tsv => {
+                writeln!(out, "# axis\tscale\tmin\tmax\tunit\tdropped")?;
+                for (name, scale, (min, max), unit, dropped) in axes {
+                    let unit = unit_name(unit).unwrap_or("");
+                    let scale = scale.name();
+                    writeln!(out, "# {name}\t{scale}\t{min}\t{max}\t{unit}\t{dropped}")?;
+                }
+                write!(out, "x")?;
+                for column in 0..data.ys.len() {
+                    write!(out, "\t{}", data.label(column))?;
+                }
+                writeln!(out)?;
+                // missing values are left empty
+                let field = |v: f64| {
+                    if v.is_finite() {
+                        v.to_string()
+                    } else {
+                        String::new()
+                    }
+                };
+                for (row, &x) in data.xs.iter().enumerate() {
+                    write!(out, "{}", field(x))?;
+                    for ys in &data.ys {
+                        write!(out, "\t{}", field(ys[row]))?;
+                    }
+                    writeln!(out)?;
+                }
+                if let Some(counts) = counts {
+                    writeln!(out)?;
+                    writeln!(out, "# points per cell, top row first")?;
+                    for row in counts {
+                        let row: Vec<_> = row.iter().map(usize::to_string).collect();
+                        writeln!(out, "{}", row.join("\t"))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("json".parse::<Emit>().unwrap(), Emit::Json);
+        assert_eq!("tsv".parse::<Emit>().unwrap(), Emit::Tsv);
+        assert!("csv".parse::<Emit>().is_err());
+    }
+
+    #[test]
+    fn json_escapes() {
+        let mut out = Vec::new();
+        json_str("a \"b\"\\c\u{1}", &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), r#""a \"b\"\\c\u0001""#);
+    }
+}
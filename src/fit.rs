@@ -0,0 +1,324 @@
+use crate::canvas::Cell;
+use crate::data::FIT_MARK;
+use crate::{Canvas, Frame, PAD};
+use std::fmt;
+use std::str::FromStr;
+
+/// The highest degree of polynomial we'll fit.
+///
+/// Beyond this, the fit mostly follows the noise, and solving for it gets numerically shaky.
+const MAX_DEGREE: usize = 8;
+
+/// The kind of curve to fit to each Y column.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Fit {
+    /// `y = ax + b`
+    Linear,
+    /// `y = a * e^(bx)`, fit to the logarithm of the (positive) Y values.
+    Exp,
+    /// A polynomial of the given degree.
+    Poly(usize),
+}
+
+impl FromStr for Fit {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Fit::Linear),
+            "exp" => Ok(Fit::Exp),
+            _ => {
+                let Some(degree) = s.strip_prefix("poly:") else {
+                    eyre::bail!("unknown fit '{s}'; expected linear, exp, or poly:N");
+                };
+                let degree: usize = degree
+                    .parse()
+                    .map_err(|e| eyre::eyre!("invalid polynomial degree '{degree}': {e}"))?;
+                eyre::ensure!(
+                    (1..=MAX_DEGREE).contains(&degree),
+                    "polynomial degree must be between 1 and {MAX_DEGREE}, got {degree}"
+                );
+                Ok(Fit::Poly(degree))
+            }
+        }
+    }
+}
+
+impl Fit {
+    /// Finds the least-squares fit of this kind to the points `(xs[i], ys[i])`.
+    ///
+    /// Points with a missing X or Y (or, for [`Fit::Exp`], a Y that isn't positive) are ignored.
+    /// Returns `None` if there aren't enough points left to pin down the fit.
+    pub(crate) fn to(self, xs: &[f64], ys: &[f64]) -> Option<Model> {
+        let (xs, ys): (Vec<f64>, Vec<f64>) = xs
+            .iter()
+            .zip(ys)
+            .filter(|&(x, y)| x.is_finite() && y.is_finite() && (self != Fit::Exp || *y > 0.))
+            .unzip();
+
+        // x is shifted and scaled into about [-1, 1] before fitting, since raising (say) unix
+        // timestamps to even the second power makes for a hopelessly ill-conditioned system.
+        let min = xs.iter().copied().min_by(f64::total_cmp)?;
+        let max = xs.iter().copied().max_by(f64::total_cmp)?;
+        let center = (min + max) / 2.;
+        let spread = if max > min { (max - min) / 2. } else { 1. };
+        let us: Vec<_> = xs.iter().map(|x| (x - center) / spread).collect();
+
+        let (degree, targets) = match self {
+            Fit::Linear => (1, ys.clone()),
+            Fit::Exp => (1, ys.iter().map(|y| y.ln()).collect()),
+            Fit::Poly(degree) => (degree, ys.clone()),
+        };
+        let coefficients = least_squares(&us, &targets, degree)?;
+
+        let mut model = Model {
+            fit: self,
+            center,
+            spread,
+            coefficients,
+            r_squared: f64::NAN,
+        };
+        let mean = ys.iter().sum::<f64>() / ys.len() as f64;
+        let total: f64 = ys.iter().map(|y| (y - mean).powi(2)).sum();
+        let residual: f64 = xs
+            .iter()
+            .zip(&ys)
+            .map(|(&x, y)| (y - model.at(x)).powi(2))
+            .sum();
+        model.r_squared = if total > 0. {
+            1. - residual / total
+        } else if residual == 0. {
+            // a perfect fit to a flat line
+            1.
+        } else {
+            f64::NAN
+        };
+        Some(model)
+    }
+}
+
+/// A curve fit to a Y column by [`Fit::to`].
+#[derive(Debug, Clone)]
+pub(crate) struct Model {
+    fit: Fit,
+    /// The X values were fit as `(x - center) / spread`.
+    center: f64,
+    spread: f64,
+    /// The coefficients of the polynomial in the shifted and scaled X, lowest power first.
+    ///
+    /// For [`Fit::Exp`], this is the polynomial for the logarithm of Y.
+    coefficients: Vec<f64>,
+    r_squared: f64,
+}
+
+impl Model {
+    /// Evaluates the fitted curve at `x`.
+    pub(crate) fn at(&self, x: f64) -> f64 {
+        let u = (x - self.center) / self.spread;
+        let y = self.coefficients.iter().rev().fold(0., |y, c| y * u + c);
+        if self.fit == Fit::Exp {
+            y.exp()
+        } else {
+            y
+        }
+    }
+
+    /// The coefficient of determination of the fit.
+    pub(crate) fn r_squared(&self) -> f64 {
+        self.r_squared
+    }
+
+    /// Draws the fitted curve across the frame with [`FIT_MARK`], leaving out the parts that fall
+    /// outside it.
+    ///
+    /// Like [`FAINT_MARK`](crate::data::FAINT_MARK), this should be drawn before the data points
+    /// so they aren't hidden by it.
+    pub(crate) fn draw_into(&self, canvas: &mut Canvas, using: &Frame) {
+        for column in 0..=canvas.columns().saturating_sub(PAD) {
+            let x = using.column_to_x(column);
+            let Some((row, column)) = using.visible_cell((x, self.at(x))) else {
+                continue;
+            };
+            if let Some(cell) = canvas.cell(row, column) {
+                *cell = Cell::Glyph(FIT_MARK);
+            }
+        }
+    }
+
+    /// The coefficients of the polynomial in X (rather than in the shifted and scaled X), lowest
+    /// power first.
+    fn coefficients_in_x(&self) -> Vec<f64> {
+        // substitute u = (x - center) / spread = x / spread - center / spread, and expand.
+        let (a, b) = (1. / self.spread, -self.center / self.spread);
+        let mut in_x = vec![0.; self.coefficients.len()];
+        // (ax + b)^k, lowest power first
+        let mut power = vec![1.];
+        for &c in &self.coefficients {
+            for (i, p) in power.iter().enumerate() {
+                in_x[i] += c * p;
+            }
+            let mut next = vec![0.; power.len() + 1];
+            for (i, p) in power.iter().enumerate() {
+                next[i] += b * p;
+                next[i + 1] += a * p;
+            }
+            power = next;
+        }
+        in_x
+    }
+}
+
+/// Writes the fitted curve as an equation (like `y = 1.5x + 2`).
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let in_x = self.coefficients_in_x();
+        if self.fit == Fit::Exp {
+            let (a, b) = (in_x[0].exp(), in_x[1]);
+            return write!(f, "y = {} * e^({}x)", significant(a), significant(b));
+        }
+        // terms that contribute nothing over the range of X that was fit are (just about always)
+        // rounding errors, and only make the equation harder to read.
+        let extent = self.center.abs() + self.spread;
+        let contribution = |power: usize, c: f64| c.abs() * extent.powi(power as i32);
+        let largest = (0..in_x.len())
+            .map(|power| contribution(power, in_x[power]))
+            .fold(0., f64::max);
+
+        write!(f, "y =")?;
+        let mut first = true;
+        for (power, &c) in in_x.iter().enumerate().rev() {
+            let negligible = contribution(power, c) <= largest * 1e-12;
+            if negligible && !(first && power == 0) {
+                continue;
+            }
+            let sign = if c < 0. { "-" } else { "+" };
+            if first {
+                write!(f, " {}", if c < 0. { "-" } else { "" })?;
+            } else {
+                write!(f, " {sign} ")?;
+            }
+            write!(f, "{}", significant(c.abs()))?;
+            match power {
+                0 => {}
+                1 => write!(f, "x")?,
+                _ => write!(f, "x^{power}")?,
+            }
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// Rounds `v` to 4 significant digits for display.
+pub(crate) fn significant(v: f64) -> String {
+    if v == 0. || !v.is_finite() {
+        return format!("{v}");
+    }
+    if !(1e-4..1e9).contains(&v.abs()) {
+        return format!("{v:.3e}");
+    }
+    let decimals = 3 - v.abs().log10().floor() as i32;
+    let rounded = if decimals > 0 {
+        let scale = 10f64.powi(decimals);
+        (v * scale).round() / scale
+    } else {
+        let unit = 10f64.powi(-decimals);
+        (v / unit).round() * unit
+    };
+    format!("{rounded}")
+}
+
+/// Finds the coefficients (lowest power first) of the polynomial of the given degree that best
+/// fits the points `(us[i], ys[i])` in the least-squares sense.
+///
+/// Returns `None` if the points don't determine a single best fit.
+fn least_squares(us: &[f64], ys: &[f64], degree: usize) -> Option<Vec<f64>> {
+    let n = degree + 1;
+    // the normal equations: (V^T V) c = V^T y, where V is the Vandermonde matrix of us
+    let mut system = vec![vec![0.; n + 1]; n];
+    for (&u, &y) in us.iter().zip(ys) {
+        let powers: Vec<_> = (0..n).map(|i| u.powi(i as i32)).collect();
+        for (i, row) in system.iter_mut().enumerate() {
+            for j in 0..n {
+                row[j] += powers[i] * powers[j];
+            }
+            row[n] += powers[i] * y;
+        }
+    }
+
+    // gaussian elimination with partial pivoting
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&a, &b| system[a][column].abs().total_cmp(&system[b][column].abs()))?;
+        if system[pivot][column].abs() < 1e-12 {
+            // too few distinct points for this degree
+            return None;
+        }
+        system.swap(column, pivot);
+        let (done, rest) = system.split_at_mut(column + 1);
+        let pivot_row = &done[column];
+        for row in rest {
+            let factor = row[column] / pivot_row[column];
+            for (v, p) in row.iter_mut().zip(pivot_row).skip(column) {
+                *v -= factor * p;
+            }
+        }
+    }
+    let mut coefficients = vec![0.; n];
+    for row in (0..n).rev() {
+        let known: f64 = (row + 1..n).map(|k| system[row][k] * coefficients[k]).sum();
+        coefficients[row] = (system[row][n] - known) / system[row][row];
+    }
+    Some(coefficients)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("linear".parse::<Fit>().unwrap(), Fit::Linear);
+        assert_eq!("exp".parse::<Fit>().unwrap(), Fit::Exp);
+        assert_eq!("poly:3".parse::<Fit>().unwrap(), Fit::Poly(3));
+        assert!("poly:0".parse::<Fit>().is_err());
+        assert!("poly:x".parse::<Fit>().is_err());
+        assert!("cubic".parse::<Fit>().is_err());
+    }
+
+    #[test]
+    fn linear() {
+        let xs: Vec<_> = (0..10).map(f64::from).collect();
+        let ys: Vec<_> = xs.iter().map(|x| 1.5 * x - 2.).collect();
+        let model = Fit::Linear.to(&xs, &ys).unwrap();
+        assert!((model.at(20.) - 28.).abs() < 1e-9);
+        assert!((model.r_squared() - 1.).abs() < 1e-9);
+        assert_eq!(model.to_string(), "y = 1.5x - 2");
+    }
+
+    #[test]
+    fn exp() {
+        let xs: Vec<_> = (0..10).map(f64::from).collect();
+        let ys: Vec<_> = xs.iter().map(|x| 3. * (0.5 * x).exp()).collect();
+        let model = Fit::Exp.to(&xs, &ys).unwrap();
+        assert!((model.at(4.) - 3. * 2f64.exp()).abs() < 1e-6);
+        assert_eq!(model.to_string(), "y = 3 * e^(0.5x)");
+    }
+
+    #[test]
+    fn poly_far_from_zero() {
+        // unix timestamps, where fitting in x directly would fall apart
+        let xs: Vec<_> = (0..20).map(|i| 1.7e9 + f64::from(i)).collect();
+        let ys: Vec<_> = (0..20).map(|i| f64::from(i * i) - 3.).collect();
+        let model = Fit::Poly(2).to(&xs, &ys).unwrap();
+        assert!((model.at(1.7e9 + 30.) - 897.).abs() < 1e-3);
+        assert!((model.r_squared() - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn too_few_points() {
+        assert!(Fit::Linear.to(&[1., 1.], &[2., 3.]).is_none());
+        assert!(Fit::Poly(3).to(&[1., 2., 3.], &[2., 3., 1.]).is_none());
+        assert!(Fit::Exp.to(&[1., 2.], &[-1., 0.]).is_none());
+    }
+}
@@ -0,0 +1,298 @@
+use crate::args::Opt;
+use crate::parse::{ParseOptions, Reader, Row};
+use crate::Data;
+use crossterm::{cursor, queue, terminal};
+use eyre::Context;
+use std::fs::{File, Metadata};
+use std::io::{BufReader, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How long to wait before looking for more input once there's none left.
+const POLL: Duration = Duration::from_millis(250);
+
+/// The fewest rows to keep before trimming them down to the window again, so that small windows
+/// aren't trimmed on every row.
+const TRIM_AFTER: usize = 1024;
+
+/// How much of a followed input to keep around (and plot).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Window {
+    /// The last this many rows.
+    Rows(usize),
+    /// The rows with an X no more than this far behind that of the last row.
+    X(f64),
+}
+
+impl FromStr for Window {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(span) = s.strip_prefix("x:") {
+            let span: f64 = span
+                .parse()
+                .map_err(|e| eyre::eyre!("invalid span of X '{span}': {e}"))?;
+            eyre::ensure!(
+                span > 0. && span.is_finite(),
+                "span of X must be positive, got {span}"
+            );
+            return Ok(Window::X(span));
+        }
+        let rows: usize = s
+            .parse()
+            .map_err(|e| eyre::eyre!("expected N or x:T, got '{s}': {e}"))?;
+        eyre::ensure!(rows > 0, "window must hold at least one row");
+        Ok(Window::Rows(rows))
+    }
+}
+
+impl Window {
+    /// Drops the rows of `data` that have fallen out of the window.
+    pub(crate) fn apply(self, data: &mut Data) {
+        let stale = match self {
+            Window::Rows(rows) => data.xs.len().saturating_sub(rows),
+            Window::X(span) => {
+                // NOTE: this assumes X (mostly) grows, as it does for anything followed over time
+                let Some(&last) = data.xs.iter().rev().find(|x| x.is_finite()) else {
+                    return;
+                };
+                data.xs
+                    .iter()
+                    .position(|&x| x >= last - span)
+                    .unwrap_or(data.xs.len())
+            }
+        };
+        data.drop_first(stale);
+    }
+}
+
+/// The rows of a followed input, trimmed to the window as they come in so that however much input
+/// there is (say, when starting on a large file, or after a burst), only so much is kept.
+#[derive(Debug, Default)]
+struct Followed {
+    data: Data,
+    /// How many rows there have been across every file, which is the X of the next row with -x.
+    rows: usize,
+    /// How many rows were left when the window was last applied.
+    kept: usize,
+}
+
+impl Followed {
+    /// Adds `row` (from input line `line`), and drops the rows that have fallen out of `window`
+    /// once there are twice as many as were left the last time, so never more than about twice
+    /// what the window holds.
+    fn push(&mut self, row: &Row, x_is_row: bool, line: usize, window: Window) {
+        let x = x_is_row.then_some(self.rows as f64);
+        self.data.push_row(row, x, line);
+        self.rows += 1;
+        if self.data.xs.len() >= 2 * self.kept.max(TRIM_AFTER) {
+            self.trim(window);
+        }
+    }
+
+    /// Drops the rows that have fallen out of `window`.
+    fn trim(&mut self, window: Window) {
+        window.apply(&mut self.data);
+        self.kept = self.data.xs.len();
+    }
+}
+
+/// Something that identifies the file behind a path, so it can be told when it is replaced.
+#[cfg(unix)]
+fn identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Elsewhere, only truncation can be told apart.
+#[cfg(not(unix))]
+fn identity(_: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// A file being followed, like `tail -F` does.
+struct Source {
+    path: PathBuf,
+    reader: Reader<BufReader<File>>,
+    identity: Option<(u64, u64)>,
+    /// The length of the file when last checked, to tell when it is truncated.
+    len: u64,
+}
+
+impl Source {
+    fn open(path: &Path, options: ParseOptions) -> eyre::Result<Self> {
+        let file = File::open(path).context("open")?;
+        let metadata = file.metadata().context("read metadata")?;
+        Ok(Self {
+            path: path.to_owned(),
+            reader: Reader::new(BufReader::new(file), options).following(),
+            identity: identity(&metadata),
+            len: metadata.len(),
+        })
+    }
+
+    /// Whether the file at the path was rotated since it was opened (or last checked), either
+    /// by being replaced or by being truncated.
+    fn rotated(&mut self) -> eyre::Result<bool> {
+        let metadata = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // in the middle of being rotated; the new file just isn't there yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e).context("read metadata"),
+        };
+        let replaced = identity(&metadata) != self.identity;
+        let truncated = metadata.len() < self.len;
+        self.len = metadata.len();
+        Ok(replaced || truncated)
+    }
+}
+
+/// Plots the rows in the file at `path` as it grows, re-drawing whenever rows are added (or the
+/// terminal is resized), until interrupted.
+///
+/// Only the rows within the window are kept, so this runs in bounded memory.
+pub(crate) fn run(path: &Path, opt: &Opt) -> eyre::Result<()> {
+    let terminal = std::io::stdout().is_terminal();
+
+    let mut source = Source::open(path, opt.parse)?;
+    let mut followed = Followed::default();
+    // the line within the current file
+    let mut line = 0;
+    // the size the plot was last drawn at, if it has been drawn
+    let mut drawn_at = None;
+    loop {
+        // the terminal may have been resized since the last time around
+        let (width, height) = opt.size();
+        // by default, keep one row per column, so the plot scrolls by one column per row
+        let window = opt.window.unwrap_or(Window::Rows(width));
+
+        let mut grew = false;
+        for row in source.reader.by_ref() {
+            line += 1;
+            let row = row.with_context(|| format!("read line {line}"))?;
+            followed.push(&row, opt.x_is_row, line, window);
+            grew = true;
+        }
+
+        if grew || drawn_at.is_some_and(|size| size != (width, height)) {
+            drawn_at = Some((width, height));
+            followed.trim(window);
+            let plot = crate::transform_rows(followed.data.clone(), opt, width)?;
+            let mut drawn = Vec::new();
+            crate::plot(plot, opt, (width, height), &mut drawn)?;
+
+            let stdout = std::io::stdout();
+            let mut stdout = stdout.lock();
+            if terminal {
+                queue!(
+                    stdout,
+                    terminal::Clear(terminal::ClearType::All),
+                    cursor::MoveTo(0, 0)
+                )?;
+            }
+            stdout.write_all(&drawn)?;
+            stdout.flush()?;
+        } else if source.rotated()? {
+            // whatever was written to the old file before it was rotated has been read by now
+            source = Source::open(path, opt.parse).context("reopen after rotation")?;
+            line = 0;
+        } else {
+            std::thread::sleep(POLL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("100".parse::<Window>().unwrap(), Window::Rows(100));
+        assert_eq!("x:60".parse::<Window>().unwrap(), Window::X(60.));
+        assert!("0".parse::<Window>().is_err());
+        assert!("x:-1".parse::<Window>().is_err());
+        assert!("x:".parse::<Window>().is_err());
+    }
+
+    #[test]
+    fn apply() {
+        let data = Data {
+            xs: vec![1., 2., 4., 8., 9.],
+            ys: vec![vec![10., 20., 40., 80., 90.]],
+            lines: vec![1, 2, 3, 4, 5],
+            ..Data::default()
+        };
+
+        let mut rows = data.clone();
+        Window::Rows(2).apply(&mut rows);
+        assert_eq!(rows.xs, [8., 9.]);
+        assert_eq!(rows.ys, [[80., 90.]]);
+        assert_eq!(rows.lines, [4, 5]);
+
+        let mut span = data.clone();
+        Window::X(5.).apply(&mut span);
+        assert_eq!(span.xs, [4., 8., 9.]);
+        assert_eq!(span.ys, [[40., 80., 90.]]);
+    }
+
+    #[test]
+    fn memory_is_bounded_by_the_window() {
+        let row = Row {
+            values: vec![1., 2.],
+            units: Vec::new(),
+        };
+        for window in [Window::Rows(10), Window::Rows(5000), Window::X(100.)] {
+            let mut followed = Followed::default();
+            let mut most = 0;
+            for line in 1..=100_000 {
+                followed.push(&row, true, line, window);
+                most = most.max(followed.data.xs.len());
+            }
+            let holds = match window {
+                Window::Rows(rows) => rows,
+                Window::X(span) => span as usize + 1,
+            };
+            assert!(most <= 2 * holds.max(TRIM_AFTER), "{window:?}: {most}");
+
+            followed.trim(window);
+            assert_eq!(followed.data.xs.len(), holds, "{window:?}");
+            assert_eq!(followed.data.xs.last(), Some(&99_999.));
+            assert_eq!(followed.data.lines.last(), Some(&100_000));
+        }
+    }
+
+    #[test]
+    fn reopens_after_rotation() {
+        let dir = std::env::temp_dir().join(format!("dings-follow-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("input");
+        let rows = |source: &mut Source| source.reader.by_ref().map(Result::unwrap).count();
+
+        std::fs::write(&path, "1\n2\n").unwrap();
+        let mut source = Source::open(&path, ParseOptions::default()).unwrap();
+        assert_eq!(rows(&mut source), 2);
+        assert!(!source.rotated().unwrap());
+
+        // truncated, and then written to again
+        std::fs::write(&path, "3\n").unwrap();
+        assert!(source.rotated().unwrap());
+        let mut source = Source::open(&path, ParseOptions::default()).unwrap();
+        assert_eq!(rows(&mut source), 1);
+        assert!(!source.rotated().unwrap());
+
+        // replaced by a new file, as by logrotate
+        let rotated = dir.join("input.1");
+        std::fs::rename(&path, &rotated).unwrap();
+        assert!(!source.rotated().unwrap(), "not there yet");
+        std::fs::write(&path, "4\n5\n6\n").unwrap();
+        if cfg!(unix) {
+            assert!(source.rotated().unwrap());
+        }
+        let mut source = Source::open(&path, ParseOptions::default()).unwrap();
+        assert_eq!(rows(&mut source), 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
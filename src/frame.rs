@@ -1,11 +1,22 @@
-use crate::{Canvas, Data};
+use crate::canvas::{Cell, Charset};
+use crate::scale::Scale;
+use crate::{Canvas, Data, Mode};
 
 pub const PAD: usize = 2;
 
+/// Where on the canvas each point goes.
+///
+/// The bounds are kept along the axes as drawn, so after the [`Scale`] of each axis is applied.
+#[derive(Debug, Clone)]
 pub(crate) struct Frame {
     width: usize,
     height: usize,
 
+    x_scale: Scale,
+    y_scale: Scale,
+    /// How many (finite) values along each axis could not be drawn on its scale.
+    dropped: (usize, usize),
+
     min_x: f64,
     max_x: f64,
     range_x: f64,
@@ -13,43 +24,58 @@ pub(crate) struct Frame {
     min_y: f64,
     max_y: f64,
     range_y: f64,
+
+    /// Whether the bounds were narrowed to a view of the data (see [`Frame::zoomed`]), so that
+    /// points may fall outside of them.
+    clipped: bool,
 }
 
 impl Frame {
     pub(crate) fn new_over(width: usize, height: usize, data: &Data) -> Self {
-        let min_x = data
-            .xs
-            .iter()
-            .filter(|v| v.is_finite())
-            .copied()
-            .min_by(f64::total_cmp);
-        let max_x = data
-            .xs
-            .iter()
-            .filter(|v| v.is_finite())
-            .copied()
-            .max_by(f64::total_cmp);
-        let min_y = data
-            .ys
-            .iter()
-            .flatten()
-            .filter(|v| v.is_finite())
-            .copied()
-            .min_by(f64::total_cmp);
-        let max_y = data
-            .ys
-            .iter()
-            .flatten()
-            .filter(|v| v.is_finite())
-            .copied()
-            .max_by(f64::total_cmp);
-
-        let (Some(mut min_x), Some(max_x)) = (min_x, max_x) else {
-            todo!();
-        };
-        let (Some(mut min_y), Some(max_y)) = (min_y, max_y) else {
-            todo!();
-        };
+        Self::new_scaled_over(width, height, data, Scale::Linear, Scale::Linear)
+    }
+
+    /// Like [`Frame::new_over`], but with the given scale along each axis.
+    pub(crate) fn new_scaled_over(
+        width: usize,
+        height: usize,
+        data: &Data,
+        x_scale: Scale,
+        y_scale: Scale,
+    ) -> Self {
+        // values that can't be drawn on the scale of their axis are left out, but counted (as
+        // however many points of the input their row stands for)
+        let scaled =
+            |vs: &mut dyn Iterator<Item = (usize, &f64)>, scale: Scale, dropped: &mut usize| {
+                let finite = vs.filter(|(_, v)| v.is_finite());
+                let scaled: Vec<_> = finite
+                    .filter_map(|(row, &v)| {
+                        let at = scale.apply(v);
+                        if at.is_none() {
+                            *dropped += data.weight(row);
+                        }
+                        at
+                    })
+                    .collect();
+                scaled
+            };
+        let mut dropped = (0, 0);
+        let xs = scaled(&mut data.xs.iter().enumerate(), x_scale, &mut dropped.0);
+        let ys = scaled(
+            &mut data.ys.iter().flat_map(|ys| ys.iter().enumerate()),
+            y_scale,
+            &mut dropped.1,
+        );
+
+        let min_x = xs.iter().copied().min_by(f64::total_cmp);
+        let max_x = xs.iter().copied().max_by(f64::total_cmp);
+        let min_y = ys.iter().copied().min_by(f64::total_cmp);
+        let max_y = ys.iter().copied().max_by(f64::total_cmp);
+
+        // with no (finite) values along an axis, there is nothing to plot along it. we still want
+        // to draw an (empty) frame though, so just pick something.
+        let (mut min_x, max_x) = min_x.zip(max_x).unwrap_or((0., 1.));
+        let (mut min_y, max_y) = min_y.zip(max_y).unwrap_or((0., 1.));
 
         /* Override bounds that would lead to a range of zero, to avoid a
          * crash when plotting. (Found by afl.) */
@@ -90,43 +116,228 @@ impl Frame {
         Self {
             width,
             height,
+            x_scale,
+            y_scale,
+            dropped,
             min_x,
             max_x,
             range_x,
             min_y,
             max_y,
             range_y,
+            clipped: false,
+        }
+    }
+
+    /// Widens the frame (if needed) so that the given X and Y values (in the units of the data)
+    /// fall within it. Values that can't be drawn on the axis' scale are ignored.
+    pub(crate) fn including(mut self, xs: &[f64], ys: &[f64]) -> Self {
+        for x in xs.iter().filter_map(|&x| self.x_scale.apply(x)) {
+            self.min_x = self.min_x.min(x);
+            self.max_x = self.max_x.max(x);
+        }
+        for y in ys.iter().filter_map(|&y| self.y_scale.apply(y)) {
+            self.min_y = self.min_y.min(y);
+            self.max_y = self.max_y.max(y);
+        }
+        self.range_x = self.max_x - self.min_x;
+        self.range_y = self.max_y - self.min_y;
+        self
+    }
+
+    /// Zooms in (for a `factor` below 1) or out (above 1) around the given cell, which keeps its
+    /// place on the canvas.
+    ///
+    /// Points outside the bounds of the returned frame are left out rather than drawn.
+    pub(crate) fn zoomed(&self, (row, column): (usize, usize), factor: f64) -> Self {
+        let x = self.min_x + self.range_x * column as f64 / (self.width - PAD) as f64;
+        let y =
+            self.min_y + self.range_y * (self.height - 1 - row) as f64 / (self.height - PAD) as f64;
+        let (min_x, max_x) = (x - (x - self.min_x) * factor, x + (self.max_x - x) * factor);
+        let (min_y, max_y) = (y - (y - self.min_y) * factor, y + (self.max_y - y) * factor);
+        Self {
+            min_x,
+            max_x,
+            range_x: max_x - min_x,
+            min_y,
+            max_y,
+            range_y: max_y - min_y,
+            clipped: true,
+            ..self.clone()
+        }
+    }
+
+    /// Moves the bounds by the given fractions of their range to the right and up.
+    ///
+    /// Like with [`Frame::zoomed`], points outside the bounds of the returned frame are left out.
+    pub(crate) fn panned(&self, (right, up): (f64, f64)) -> Self {
+        let (dx, dy) = (self.range_x * right, self.range_y * up);
+        Self {
+            min_x: self.min_x + dx,
+            max_x: self.max_x + dx,
+            min_y: self.min_y + dy,
+            max_y: self.max_y + dy,
+            clipped: true,
+            ..self.clone()
+        }
+    }
+
+    /// Returns this frame, but with the bounds of `view`, as made by [`Frame::zoomed`] or
+    /// [`Frame::panned`] from a frame over the same axes.
+    pub(crate) fn viewing(&self, view: &Frame) -> Self {
+        Self {
+            min_x: view.min_x,
+            max_x: view.max_x,
+            range_x: view.range_x,
+            min_y: view.min_y,
+            max_y: view.max_y,
+            range_y: view.range_y,
+            clipped: true,
+            ..self.clone()
         }
     }
 
+    /// The width and height of the canvas the frame maps onto.
+    pub(crate) fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub(crate) fn x_scale(&self) -> Scale {
+        self.x_scale
+    }
+
+    pub(crate) fn y_scale(&self) -> Scale {
+        self.y_scale
+    }
+
+    /// How many X and Y values were left out because they can't be drawn on their axis' scale.
+    pub(crate) fn dropped(&self) -> (usize, usize) {
+        self.dropped
+    }
+
+    /// The range of X values (in the units of the data) covered by the frame.
     pub(crate) fn x_bounds(&self) -> (f64, f64) {
-        (self.min_x, self.max_x)
+        let scale = self.x_scale;
+        (scale.invert(self.min_x), scale.invert(self.max_x))
     }
 
+    /// The range of Y values (in the units of the data) covered by the frame.
     pub(crate) fn y_bounds(&self) -> (f64, f64) {
+        let scale = self.y_scale;
+        (scale.invert(self.min_y), scale.invert(self.max_y))
+    }
+
+    /// The range of the X axis as drawn (so after scaling).
+    pub(crate) fn x_axis(&self) -> (f64, f64) {
+        (self.min_x, self.max_x)
+    }
+
+    /// The range of the Y axis as drawn (so after scaling).
+    pub(crate) fn y_axis(&self) -> (f64, f64) {
         (self.min_y, self.max_y)
     }
 
-    pub(crate) fn range_xy(&self) -> (f64, f64) {
-        (self.range_x, self.range_y)
+    /// Decade ticks along the X axis as drawn, with their labels.
+    pub(crate) fn x_ticks(&self) -> Vec<(f64, String)> {
+        self.x_scale.ticks(self.min_x, self.max_x)
+    }
+
+    /// Returns how far along the X axis `x` is, from 0 at the left to 1 at the right, where `x` is
+    /// along the axis as drawn (so after scaling).
+    ///
+    /// This is the mapping behind [`Frame::x_to_column`], for drawing at a finer resolution than
+    /// one cell.
+    pub(crate) fn x_fraction(&self, x: f64) -> f64 {
+        (x - self.min_x) / self.range_x
+    }
+
+    /// Like [`Frame::x_fraction`], but for `y`, from 0 at the bottom to 1 at the top.
+    pub(crate) fn y_fraction(&self, y: f64) -> f64 {
+        (y - self.min_y) / self.range_y
+    }
+
+    /// Like [`Frame::point_to_cell`], but as fractions of the width and height of the frame (see
+    /// [`Frame::x_fraction`]).
+    pub(crate) fn point_to_fraction(&self, (x, y): (f64, f64)) -> Option<(f64, f64)> {
+        let (x, y) = (self.x_scale.apply(x)?, self.y_scale.apply(y)?);
+        Some((self.x_fraction(x), self.y_fraction(y)))
     }
 
+    /// Returns the column of `x`, which is along the axis as drawn (so after scaling).
     pub(crate) fn x_to_column(&self, x: f64) -> usize {
         let plot_width = (self.width - PAD) as f64;
-        let x_as_fraction_of_axis = (x - self.min_x) / self.range_x;
+        let x_as_fraction_of_axis = self.x_fraction(x);
         (plot_width * x_as_fraction_of_axis).round() as usize
     }
 
+    /// Returns the row of `y`, which is along the axis as drawn (so after scaling).
     pub(crate) fn y_to_row(&self, y: f64) -> usize {
         let plot_height = (self.height - PAD) as f64;
-        let y_as_fraction_of_axis = (y - self.min_y) / self.range_y;
+        let y_as_fraction_of_axis = self.y_fraction(y);
         let y_cell_from_top = (plot_height * y_as_fraction_of_axis).round() as usize;
         // flip y; 0 at bottom of plot
         self.height - y_cell_from_top - 1
     }
 
-    pub(crate) fn point_to_cell(&self, (x, y): (f64, f64)) -> (usize, usize) {
-        (self.y_to_row(y), self.x_to_column(x))
+    /// Returns the X value (in the units of the data) that lands in the middle of `column`.
+    pub(crate) fn column_to_x(&self, column: usize) -> f64 {
+        let plot_width = (self.width - PAD) as f64;
+        let x = self.min_x + self.range_x * column as f64 / plot_width;
+        self.x_scale.invert(x)
+    }
+
+    /// Returns the Y value (in the units of the data) that lands in the middle of `row`.
+    pub(crate) fn row_to_y(&self, row: usize) -> f64 {
+        let plot_height = (self.height - PAD) as f64;
+        let y = self.min_y + self.range_y * (self.height - 1 - row) as f64 / plot_height;
+        self.y_scale.invert(y)
+    }
+
+    /// Returns the column of the X value (in the units of the data), if it is within the frame.
+    pub(crate) fn column_of(&self, x: f64) -> Option<usize> {
+        let x = self.x_scale.apply(x)?;
+        (self.min_x..=self.max_x)
+            .contains(&x)
+            .then(|| self.x_to_column(x))
+    }
+
+    /// Returns the row of the Y value (in the units of the data), if it is within the frame.
+    pub(crate) fn row_of(&self, y: f64) -> Option<usize> {
+        let y = self.y_scale.apply(y)?;
+        (self.min_y..=self.max_y)
+            .contains(&y)
+            .then(|| self.y_to_row(y))
+    }
+
+    /// Like [`Frame::point_to_cell`], but also `None` for points outside of the frame's bounds.
+    pub(crate) fn visible_cell(&self, (x, y): (f64, f64)) -> Option<(usize, usize)> {
+        Some((self.row_of(y)?, self.column_of(x)?))
+    }
+
+    /// Returns the cell (as row, column) of a data point, if it can be drawn on this frame's
+    /// scales.
+    pub(crate) fn point_to_cell(&self, (x, y): (f64, f64)) -> Option<(usize, usize)> {
+        if self.clipped {
+            return self.visible_cell((x, y));
+        }
+        let (x, y) = (self.x_scale.apply(x)?, self.y_scale.apply(y)?);
+
+        const CMP_PAD: f64 = 0.001;
+        assert!(x >= self.min_x - CMP_PAD);
+        assert!(x <= self.max_x + CMP_PAD);
+
+        Some((self.y_to_row(y), self.x_to_column(x)))
+    }
+
+    /// Whether a data point falls in the top (rather than the bottom) half of its cell (see
+    /// [`Frame::point_to_cell`]), for when cells are split in two.
+    pub(crate) fn in_top_half(&self, y: f64) -> bool {
+        let Some(y) = self.y_scale.apply(y) else {
+            return false;
+        };
+        // the middle of each cell is a whole number of cells up from the bottom of the plot
+        let cells_up = (self.height - PAD) as f64 * self.y_fraction(y);
+        cells_up >= cells_up.round()
     }
 
     pub(crate) fn draw_into(&self, canvas: &mut Canvas) {
@@ -151,56 +362,184 @@ impl Frame {
             }
         }
 
-        let draw_horizontal_at_row = self.point_to_cell((0., draw_horizontal_at_y)).0;
-        let draw_vertical_at_column = self.point_to_cell((draw_vertical_at_x, 0.)).1;
+        // NOTE: these can't go through point_to_cell, since 0 need not be within the bounds of
+        // the other axis.
+        let draw_horizontal_at_row = self.y_to_row(draw_horizontal_at_y);
+        let draw_vertical_at_column = self.x_to_column(draw_vertical_at_x);
+
+        // on a linear axis, ticks go every 5 cells. otherwise, they go at every decade.
+        let x_ticks: Vec<_> = self
+            .x_ticks()
+            .into_iter()
+            .map(|(x, _)| self.x_to_column(x))
+            .collect();
+        let y_ticks: Vec<_> = self
+            .y_scale
+            .ticks(self.min_y, self.max_y)
+            .into_iter()
+            .map(|(y, label)| (self.y_to_row(y), label))
+            .collect();
+        let is_x_tick = |column: usize| {
+            if self.x_scale.is_linear() {
+                column.is_multiple_of(5)
+            } else {
+                x_ticks.contains(&column)
+            }
+        };
+        let is_y_tick = |row: usize| {
+            if self.y_scale.is_linear() {
+                row.is_multiple_of(5)
+            } else {
+                y_ticks.iter().any(|&(r, _)| r == row)
+            }
+        };
+
+        // the glyphs for the axes, and for the ticks along them. with box drawing, a tick sticks
+        // out on the side away from the data if the data is all on one side of the axis.
+        let (horizontal, vertical, cross, dot, x_tick, y_tick) = match canvas.charset {
+            Charset::Ascii => ('-', '|', '+', '.', '+', '+'),
+            Charset::Unicode => (
+                '─',
+                '│',
+                '┼',
+                '·',
+                if self.max_y <= 0. { '┴' } else { '┬' },
+                if self.min_x >= 0. { '┤' } else { '├' },
+            ),
+        };
 
         // draw in the axes
         // draw the vertical (Y) axis (so where X = 0)
         for row in 0..self.height {
             #[allow(clippy::collapsible_else_if)]
             let c = if x0_is_visible {
-                if row % 5 == 0 {
-                    b'+'
+                if is_y_tick(row) {
+                    y_tick
                 } else {
-                    b'|'
+                    vertical
                 }
             } else {
-                if row % 5 == 0 {
-                    b'.'
+                if is_y_tick(row) {
+                    dot
                 } else {
-                    b' '
+                    ' '
                 }
             };
             let Some(cell) = canvas.cell(row, draw_vertical_at_column) else {
-                panic!("invalid cell ({row}, {draw_vertical_at_column}) for axis component ({draw_vertical_at_x}, _)");
+                panic!(
+                    "invalid cell ({row}, {draw_vertical_at_column}) \
+                     for axis component ({draw_vertical_at_x}, _)"
+                );
             };
-            *cell = c;
+            *cell = Cell::Glyph(c);
         }
         // draw the horizontal (X) axis (so where Y = 0)
         for column in 0..self.width {
             #[allow(clippy::collapsible_else_if)]
             let c = if y0_is_visible {
-                if column % 5 == 0 {
-                    b'+'
+                if is_x_tick(column) {
+                    x_tick
                 } else {
-                    b'-'
+                    horizontal
                 }
             } else {
-                if column % 5 == 0 {
-                    b'.'
+                if is_x_tick(column) {
+                    dot
                 } else {
-                    b' '
+                    ' '
                 }
             };
             let Some(cell) = canvas.cell(draw_horizontal_at_row, column) else {
-                panic!("invalid cell ({draw_horizontal_at_row}, {column}) for axis component ({draw_horizontal_at_y}, _)");
+                panic!(
+                    "invalid cell ({draw_horizontal_at_row}, {column}) \
+                     for axis component ({draw_horizontal_at_y}, _)"
+                );
             };
-            *cell = c;
+            *cell = Cell::Glyph(c);
         }
-        // where the axes meet, put a +
+        // where the axes meet, put a cross
         let intersection = canvas
             .cell(draw_horizontal_at_row, draw_vertical_at_column)
             .expect("must have hit one of the panics above");
-        *intersection = b'+';
+        *intersection = Cell::Glyph(cross);
+
+        // the X ticks are labelled underneath the canvas, but there's no room for the Y ones
+        // outside of it, so they go just right of the axis. in count mode, the labels would be
+        // mistaken for counts, so there they are left out.
+        if let Mode::Dot = canvas.mode {
+            for (row, label) in y_ticks {
+                canvas.write_str(row, draw_vertical_at_column + 1, &label);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use quickcheck::{quickcheck, TestResult};
+
+    /// Builds a single-series data set out of the finite points, if there are any.
+    pub(crate) fn finite_data(points: Vec<(f64, f64)>) -> Option<Data> {
+        let (xs, ys): (Vec<_>, Vec<_>) = points
+            .into_iter()
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .unzip();
+        (!xs.is_empty()).then(|| Data {
+            xs,
+            ys: vec![ys],
+            ..Data::default()
+        })
+    }
+
+    quickcheck! {
+        fn points_land_on_canvas(points: Vec<(f64, f64)>, width: u8, height: u8) -> TestResult {
+            let Some(data) = finite_data(points) else {
+                return TestResult::discard();
+            };
+            let (width, height) = (PAD + 1 + usize::from(width), PAD + 1 + usize::from(height));
+            let frame = Frame::new_over(width, height, &data);
+            for (&x, &y) in data.xs.iter().zip(&data.ys[0]) {
+                let column = frame.x_to_column(x);
+                let row = frame.y_to_row(y);
+                if column >= width || row >= height {
+                    return TestResult::error(format!(
+                        "({x}, {y}) landed at ({row}, {column}) on a {width}x{height} canvas"
+                    ));
+                }
+            }
+            TestResult::passed()
+        }
+
+        fn zoomed_points_are_clipped(points: Vec<(f64, f64)>, row: u8, column: u8) -> TestResult {
+            let Some(data) = finite_data(points) else {
+                return TestResult::discard();
+            };
+            let (width, height) = (40, 20);
+            let (row, column) = (usize::from(row) % height, usize::from(column) % width);
+            let frame = Frame::new_over(width, height, &data).zoomed((row, column), 0.5);
+            for (&x, &y) in data.xs.iter().zip(&data.ys[0]) {
+                if let Some((r, c)) = frame.point_to_cell((x, y)) {
+                    if r >= height || c >= width {
+                        return TestResult::error(format!(
+                            "({x}, {y}) landed at ({r}, {c}) on a {width}x{height} canvas"
+                        ));
+                    }
+                }
+            }
+            TestResult::passed()
+        }
+    }
+
+    #[test]
+    fn zoom_keeps_cursor_in_place() {
+        let data = finite_data(vec![(0., 0.), (100., 50.)]).unwrap();
+        let frame = Frame::new_over(2 + 100, 2 + 50, &data);
+        let zoomed = frame.zoomed((21, 40), 0.5);
+        assert_eq!(zoomed.column_to_x(40), frame.column_to_x(40));
+        assert_eq!(zoomed.row_to_y(21), frame.row_to_y(21));
+        assert_eq!(zoomed.x_bounds(), (20., 70.));
+        assert_eq!(zoomed.panned((0.5, 0.)).x_bounds(), (45., 95.));
+        assert_eq!(zoomed.point_to_cell((90., 30.)), None);
     }
 }
@@ -0,0 +1,282 @@
+use crate::raster::{Raster, Rgb};
+use base64::Engine;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+
+/// How long to wait for the terminal to answer whether it can show images.
+///
+/// Terminals answer right away, so this only needs to cover a slow connection.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The size (in pixels) of a cell, if the terminal won't say.
+const DEFAULT_CELL: (usize, usize) = (10, 20);
+
+/// How much base64 (of a PNG) the kitty protocol takes in a single escape sequence.
+const KITTY_CHUNK: usize = 4096;
+
+/// A way for a terminal to show an image inline.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Protocol {
+    /// DEC sixels, as in xterm (with `-ti vt340`), foot, WezTerm, and others.
+    Sixel,
+    /// The kitty graphics protocol, as in kitty, WezTerm, Ghostty, and others.
+    Kitty,
+}
+
+/// Puts the terminal into raw mode for as long as this lives, so its answers to queries can be
+/// read as they come rather than a line at a time, and aren't echoed.
+struct RawMode;
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
+
+/// Sends `request` to the terminal (which must end with a request for the primary device
+/// attributes), and returns everything it answers.
+///
+/// Every terminal answers the request for device attributes (with `ESC [ ? ... c`), and answers
+/// in order, so once that answer is in, so is the answer to anything asked before it.
+#[cfg(unix)]
+fn query(request: &[u8]) -> Option<Vec<u8>> {
+    use std::io::Read;
+
+    let mut tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    crossterm::terminal::enable_raw_mode().ok()?;
+    let _raw = RawMode;
+    tty.write_all(request).ok()?;
+    tty.flush().ok()?;
+
+    // reads block, so they happen on a thread that's simply left behind if the terminal doesn't
+    // answer in time.
+    let mut reader = tty.try_clone().ok()?;
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = [0; 256];
+        while let Ok(n @ 1..) = reader.read(&mut buf) {
+            if tx.send(buf[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let deadline = std::time::Instant::now() + QUERY_TIMEOUT;
+    let mut answer = Vec::new();
+    while device_attributes(&answer).is_none() {
+        let left = deadline.checked_duration_since(std::time::Instant::now())?;
+        answer.extend(rx.recv_timeout(left).ok()?);
+    }
+    Some(answer)
+}
+
+/// Elsewhere, there's no terminal to ask, so it's assumed it can't show images.
+#[cfg(not(unix))]
+fn query(_: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+/// Picks the primary device attributes (as in `ESC [ ? 62 ; 4 ; 22 c`) out of what the terminal
+/// answered, if they're in there.
+fn device_attributes(answer: &[u8]) -> Option<Vec<&[u8]>> {
+    let start = answer.windows(3).rposition(|w| w == b"\x1b[?")? + 3;
+    let len = answer[start..].iter().position(|&b| b == b'c')?;
+    Some(answer[start..start + len].split(|&b| b == b';').collect())
+}
+
+impl Protocol {
+    /// Asks the terminal on stdout whether it can show images this way.
+    pub(crate) fn supported(self) -> bool {
+        if !std::io::stdout().is_terminal() {
+            return false;
+        }
+        match self {
+            Protocol::Sixel => {
+                let Some(answer) = query(b"\x1b[c") else {
+                    return false;
+                };
+                // attribute 4 is sixel graphics
+                device_attributes(&answer).is_some_and(|attrs| attrs.contains(&&b"4"[..]))
+            }
+            Protocol::Kitty => {
+                // a 1x1 image to query with, which the terminal only checks rather than shows;
+                // a terminal that doesn't know the protocol just ignores it.
+                let Some(answer) = query(b"\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[c")
+                else {
+                    return false;
+                };
+                answer.windows(11).any(|w| w == b"\x1b_Gi=31;OK")
+            }
+        }
+    }
+
+    /// Writes `raster` as an image to show in place of a text plot of `columns` by `rows` cells.
+    pub(crate) fn write(
+        self,
+        raster: &Raster,
+        (columns, rows): (usize, usize),
+        mut out: impl Write,
+    ) -> eyre::Result<()> {
+        match self {
+            Protocol::Sixel => sixel(raster, &mut out)?,
+            Protocol::Kitty => {
+                let mut png = Vec::new();
+                crate::png::write(raster, &mut png)?;
+                let encoded = base64::engine::general_purpose::STANDARD.encode(png);
+                let chunks: Vec<_> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let more = u8::from(i + 1 < chunks.len());
+                    if i == 0 {
+                        // sized in cells, so it takes up the room the text plot would have
+                        write!(out, "\x1b_Ga=T,f=100,c={columns},r={rows},m={more};")?;
+                    } else {
+                        write!(out, "\x1b_Gm={more};")?;
+                    }
+                    out.write_all(chunk)?;
+                    write!(out, "\x1b\\")?;
+                }
+            }
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+}
+
+/// The size of `cells` in pixels, going by the size of the terminal on stdout.
+pub(crate) fn pixels_of((columns, rows): (usize, usize)) -> (usize, usize) {
+    let cell = match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            usize::from(size.width / size.columns),
+            usize::from(size.height / size.rows),
+        ),
+        _ => DEFAULT_CELL,
+    };
+    (columns * cell.0, rows * cell.1)
+}
+
+/// The palette color closest to `color`, from a cube of six levels of each of red, green, and
+/// blue (which includes six grays).
+fn palette_index([r, g, b]: Rgb) -> usize {
+    let level = |c: u8| (usize::from(c) * 5 + 127) / 255;
+    level(r) * 36 + level(g) * 6 + level(b)
+}
+
+/// Writes `raster` as sixels.
+///
+/// Each band of six rows of pixels is written one palette color at a time, with a character for
+/// each column of which of the six pixels in it have that color.
+fn sixel(raster: &Raster, mut out: impl Write) -> std::io::Result<()> {
+    let (width, height) = raster.size();
+    let pixels: Vec<Vec<usize>> = raster
+        .rows()
+        .map(|row| row.iter().copied().map(palette_index).collect())
+        .collect();
+
+    // keep the pixel aspect ratio at 1:1, and set the size up front
+    write!(out, "\x1bP0;1;0q\"1;1;{width};{height}")?;
+    let mut used = [false; 216];
+    for &index in pixels.iter().flatten() {
+        used[index] = true;
+    }
+    for index in (0..216).filter(|&i| used[i]) {
+        let percent = |level: usize| level * 20;
+        let (r, g, b) = (index / 36, index / 6 % 6, index % 6);
+        write!(
+            out,
+            "#{index};2;{};{};{}",
+            percent(r),
+            percent(g),
+            percent(b)
+        )?;
+    }
+
+    for band in pixels.chunks(6) {
+        let mut colors: Vec<_> = band.iter().flatten().copied().collect();
+        colors.sort_unstable();
+        colors.dedup();
+        for (i, &color) in colors.iter().enumerate() {
+            if i > 0 {
+                // back to the start of the band, to draw over it in the next color
+                write!(out, "$")?;
+            }
+            write!(out, "#{color}")?;
+            let mut run: Option<(u8, usize)> = None;
+            for column in 0..width {
+                let bits = band
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, row)| row[column] == color)
+                    .fold(0u8, |bits, (i, _)| bits | 1 << i);
+                let c = b'?' + bits;
+                run = match run {
+                    Some((same, n)) if same == c => Some((c, n + 1)),
+                    Some((other, n)) => {
+                        write_run(other, n, &mut out)?;
+                        Some((c, 1))
+                    }
+                    None => Some((c, 1)),
+                };
+            }
+            if let Some((c, n)) = run {
+                write_run(c, n, &mut out)?;
+            }
+        }
+        write!(out, "-")?;
+    }
+    write!(out, "\x1b\\")
+}
+
+/// Writes `n` of the sixel `c`, with a repeat count if that's shorter.
+fn write_run(c: u8, n: usize, mut out: impl Write) -> std::io::Result<()> {
+    if n > 3 {
+        write!(out, "!{n}{}", char::from(c))
+    } else {
+        out.write_all(&vec![c; n])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_device_attributes() {
+        let answer = b"\x1b_Gi=31;OK\x1b\\\x1b[?62;4;22c";
+        let attrs = super::device_attributes(answer).unwrap();
+        assert_eq!(attrs, [&b"62"[..], b"4", b"22"]);
+        assert!(super::device_attributes(b"\x1b[?62;4").is_none());
+    }
+
+    #[test]
+    fn kitty() {
+        let mut out = Vec::new();
+        Protocol::Kitty
+            .write(&Raster::new(4, 4), (3, 2), &mut out)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        // small enough to go in one escape sequence, placed over the given cells
+        assert!(
+            out.starts_with("\x1b_Ga=T,f=100,c=3,r=2,m=0;iVBORw0KGgo"),
+            "{out}"
+        );
+        assert!(out.ends_with("\x1b\\\n"), "{out}");
+        assert_eq!(out.matches("\x1b_G").count(), 1);
+    }
+
+    #[test]
+    fn sixel_bands() {
+        let mut out = Vec::new();
+        super::sixel(&Raster::new(8, 7), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        // white is the last color of the cube, and a full band of it is all six bits ('~'),
+        // whereas the second band only has its top pixel ('@')
+        assert_eq!(
+            out,
+            "\x1bP0;1;0q\"1;1;8;7#215;2;100;100;100#215!8~-#215!8@-\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::annotate::{self, Annotation};
+use crate::canvas::Charset;
+use crate::fit::Model;
+use crate::outliers::Outlier;
+use crate::scale::Scale;
+use crate::{Canvas, Data, Frame, Mode, MARKS};
+
+/// Number of blank columns between two panels.
+const GUTTER: usize = 1;
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum Grid {
+    /// Pick the number of rows and columns based on the number of series.
+    Auto,
+    /// Use exactly this many rows and columns of panels.
+    Fixed { rows: usize, columns: usize },
+}
+
+impl Grid {
+    fn shape(self, panels: usize) -> (usize, usize) {
+        match self {
+            Grid::Auto => {
+                // prefer wider over taller, since terminals tend to be wider than they are tall.
+                let mut columns = 1;
+                while columns * columns < panels {
+                    columns += 1;
+                }
+                (panels.div_ceil(columns).max(1), columns)
+            }
+            Grid::Fixed { rows, columns } => (rows, columns),
+        }
+    }
+}
+
+/// Draws each series of `data` as its own panel (title line + canvas) of a `width` by `height`
+/// grid.
+///
+/// If `independent_axes` is set, each panel gets a [`Frame`] computed over only its own series,
+/// and its bounds are included in the panel title. Otherwise, all panels share the bounds of
+/// `data` as a whole so they can be compared directly.
+///
+/// Points are placed along each axis according to the given X and Y [`Scale`].
+///
+/// If any `fits` are given, the fitted curve of each series is drawn in its panel.
+///
+/// Each of the `outliers` is marked in the panel of its series.
+///
+/// Every panel gets all of the `annotations`, and its bounds include them.
+///
+/// If `raw` is given, its points are drawn faintly underneath those of `data`, and the bounds are
+/// computed over `raw` instead.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw(
+    data: &Data,
+    raw: Option<&Data>,
+    fits: &[Option<Model>],
+    annotations: &[Annotation],
+    outliers: &[Outlier],
+    grid: Grid,
+    independent_axes: bool,
+    draw_axes: bool,
+    (x_scale, y_scale): (Scale, Scale),
+    (width, height): (usize, usize),
+    (mode, charset): (Mode, Charset),
+) -> eyre::Result<Canvas> {
+    let panels = data.ys.len();
+    let (rows, columns) = grid.shape(panels);
+    eyre::ensure!(
+        rows * columns >= panels,
+        "a {rows}x{columns} grid only has room for {} panels, but the input has {panels} columns",
+        rows * columns
+    );
+
+    let panel_width = width.saturating_sub(GUTTER * (columns - 1)) / columns;
+    let panel_height = height / rows;
+    // one row of each panel is taken up by its title
+    let canvas_height = panel_height.saturating_sub(1);
+    eyre::ensure!(
+        panel_width > crate::PAD && canvas_height > crate::PAD,
+        "{width}x{height} is too small for a {rows}x{columns} grid"
+    );
+
+    let bounded_by = raw.unwrap_or(data);
+    let (annotation_xs, annotation_ys) = annotate::extent(annotations);
+    let frame_over = |data: &Data| {
+        Frame::new_scaled_over(panel_width, canvas_height, data, x_scale, y_scale)
+            .including(&annotation_xs, &annotation_ys)
+    };
+    let shared = frame_over(bounded_by);
+    let mut canvas = Canvas::new(height, width, mode, charset);
+    for series in 0..panels.min(MARKS.len()) {
+        let top = (series / columns) * panel_height;
+        let left = (series % columns) * (panel_width + GUTTER);
+
+        let mut bounds = String::new();
+        let own;
+        let frame = if independent_axes {
+            own = frame_over(&bounded_by.series(series));
+            let (min_x, max_x) = own.x_bounds();
+            let (min_y, max_y) = own.y_bounds();
+            bounds = format!("  {x_scale}x: [{min_x} - {max_x}]  {y_scale}y: [{min_y} - {max_y}]");
+            &own
+        } else {
+            &shared
+        };
+
+        let mut panel = Canvas::new(canvas_height, panel_width, mode, charset);
+        for annotation in annotations {
+            annotation.shade_into(&mut panel, frame);
+        }
+        if draw_axes {
+            frame.draw_into(&mut panel);
+        }
+        for annotation in annotations {
+            annotation.draw_into(&mut panel, frame);
+        }
+        if let Some(raw) = raw {
+            raw.draw_faint_series_into(&mut panel, frame, series);
+        }
+        if let Some(Some(model)) = fits.get(series) {
+            model.draw_into(&mut panel, frame);
+        }
+        data.draw_series_into(&mut panel, frame, series);
+        for outlier in outliers.iter().filter(|o| o.column == series) {
+            outlier.draw_into(data, &mut panel, frame);
+        }
+        for annotation in annotations {
+            annotation.label_into(&mut panel, frame);
+        }
+
+        // the title goes in once the panel is drawn, since that's what decides its mark
+        let title = format!("{}: {}{bounds}", data.label(series), panel.mark(series));
+        let title: String = title.chars().take(panel_width).collect();
+        canvas.write_str(top, left, &title);
+        canvas.place(top + 1, left, &panel);
+    }
+
+    Ok(canvas)
+}
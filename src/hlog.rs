@@ -0,0 +1,151 @@
+use crate::transform::{self, Quantiles};
+use crate::Data;
+use base64::Engine;
+use eyre::Context;
+use hdrhistogram::serialization::interval_log::{IntervalLogIterator, LogEntry};
+use hdrhistogram::serialization::Deserializer;
+use hdrhistogram::Histogram;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+/// How much smaller than the StartTime interval timestamps must be to be taken as relative to it.
+///
+/// This is the same heuristic (a year) as the Java `HistogramLogReader` uses.
+const RELATIVE_TO_START_TIME: f64 = 365. * 24. * 60. * 60.;
+
+/// The histograms of an HdrHistogram interval log (`.hlog`), as written by wrk2, HdrHistogram's
+/// `HistogramLogWriter`, and the `hdrhistogram` crate.
+///
+/// Each distinct tag in the log is its own column, and so are all the untagged intervals together.
+#[derive(Debug, Default)]
+pub(crate) struct Log {
+    /// The names of the columns, or empty if there are only untagged intervals.
+    labels: Vec<String>,
+    /// Each interval's start (in seconds since the start of the log), column, and histogram.
+    intervals: Vec<(f64, usize, Histogram<u64>)>,
+}
+
+impl Log {
+    pub(crate) fn read_from(mut input: impl Read) -> eyre::Result<Self> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes).context("read interval log")?;
+
+        let mut log = Log::default();
+        let (mut start_time, mut base_time) = (None, None);
+        let mut deserializer = Deserializer::new();
+        for (i, entry) in IntervalLogIterator::new(&bytes).enumerate() {
+            let interval = match entry {
+                Ok(LogEntry::StartTime(t)) => {
+                    start_time = Some(t.as_secs_f64());
+                    continue;
+                }
+                Ok(LogEntry::BaseTime(t)) => {
+                    base_time = Some(t.as_secs_f64());
+                    continue;
+                }
+                Ok(LogEntry::Interval(interval)) => interval,
+                Err(_) => {
+                    // the iterator doesn't say much more than that it's not an interval log
+                    eyre::bail!("entry {i} of the interval log is malformed");
+                }
+            };
+
+            let encoded = base64::engine::general_purpose::STANDARD
+                .decode(interval.encoded_histogram())
+                .with_context(|| format!("decode base64 of interval {i}"))?;
+            let histogram: Histogram<u64> = deserializer
+                .deserialize(&mut &encoded[..])
+                .with_context(|| format!("deserialize histogram of interval {i}"))?;
+
+            let tag = interval.tag().map(|tag| tag.as_str().to_string());
+            let column = match log.labels.iter().position(|l| Some(l) == tag.as_ref()) {
+                Some(column) => column,
+                None => {
+                    log.labels.push(tag.unwrap_or_default());
+                    log.labels.len() - 1
+                }
+            };
+
+            // see the hdrhistogram::serialization::interval_log docs for what the timestamps mean
+            let timestamp = interval.start_timestamp().as_secs_f64();
+            let timestamp = match (base_time, start_time) {
+                (Some(base), _) => base + timestamp,
+                (None, Some(start)) if timestamp < start - RELATIVE_TO_START_TIME => {
+                    start + timestamp
+                }
+                (None, _) => timestamp,
+            };
+            log.intervals.push((timestamp, column, histogram));
+        }
+
+        // plot time since the start, or, if that isn't known, since the first interval
+        let start = start_time
+            .or_else(|| {
+                log.intervals
+                    .iter()
+                    .map(|&(t, _, _)| t)
+                    .min_by(f64::total_cmp)
+            })
+            .unwrap_or(0.);
+        for (t, _, _) in &mut log.intervals {
+            *t -= start;
+        }
+        // untagged intervals have an empty tag, which makes for a poor label
+        if log.labels.len() == 1 && log.labels[0].is_empty() {
+            log.labels.clear();
+        } else if let Some(untagged) = log.labels.iter_mut().find(|l| l.is_empty()) {
+            *untagged = "untagged".to_string();
+        }
+        Ok(log)
+    }
+
+    /// Plots the distribution of all the intervals of each tag merged together.
+    ///
+    /// See [`transform::percentile_plot`].
+    pub(crate) fn percentile_plot(&self) -> eyre::Result<Data> {
+        let mut merged = vec![Histogram::new(3).expect("3 is a valid sigfig"); self.columns()];
+        for (_, column, histogram) in &self.intervals {
+            merged[*column]
+                .add(histogram)
+                .context("merge interval histograms")?;
+        }
+        Ok(Data {
+            labels: self.labels.clone(),
+            ..transform::percentile_plot_of(&merged, |v| v as f64)
+        })
+    }
+
+    /// Plots the given quantiles of each interval over time.
+    pub(crate) fn quantiles(&self, quantiles: &Quantiles) -> eyre::Result<Data> {
+        // there is only ever no label if there is only one column, which then goes unnamed
+        let labels = if self.labels.is_empty() {
+            vec![String::new()]
+        } else {
+            self.labels.clone()
+        };
+
+        // intervals of different tags that start at the same time are plotted at the same X
+        let mut buckets: BTreeMap<u64, Vec<Histogram<u64>>> = BTreeMap::new();
+        for (t, column, histogram) in &self.intervals {
+            let histograms = buckets.entry(t.to_bits()).or_insert_with(|| {
+                vec![Histogram::new(3).expect("3 is a valid sigfig"); self.columns()]
+            });
+            histograms[*column]
+                .add(histogram)
+                .context("merge interval histograms")?;
+        }
+        let mut buckets: Vec<_> = buckets
+            .into_iter()
+            .map(|(t, histograms)| (f64::from_bits(t), histograms))
+            .collect();
+        buckets.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        let mut data = quantiles.of_histograms(&labels, buckets.into_iter(), |v| v as f64)?;
+        data.x_unit.observe("s");
+        Ok(data)
+    }
+
+    fn columns(&self) -> usize {
+        self.labels.len().max(1)
+    }
+}
@@ -0,0 +1,348 @@
+use crate::annotate::Annotation;
+use crate::data::COLORS;
+use crate::parse::Unit;
+use crate::scale::Scale;
+use crate::{Data, Frame};
+use std::io::Write;
+
+/// The size of the chart, in (CSS) pixels.
+const WIDTH: f64 = 800.;
+const HEIGHT: f64 = 450.;
+
+/// Room around the plotting area for tick labels and axis titles, as (left, right, top, bottom).
+const MARGIN: (f64, f64, f64, f64) = (70., 20., 15., 50.);
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 1em; }
+svg { max-width: 100%; height: auto; }
+.area { fill: none; stroke: #888; }
+.grid { stroke: #ddd; }
+.tick, .title { font-size: 12px; fill: #333; }
+.off { opacity: 0.15; }
+.band { fill: #e4e4f2; }
+.reference { stroke: #a55; }
+.label { font-size: 12px; fill: #a55; }
+.raw { opacity: 0.3; }
+.legend { list-style: none; padding: 0; }
+.legend li { display: inline-block; margin-right: 1em; cursor: pointer; user-select: none; }
+.legend span { display: inline-block; width: 0.8em; height: 0.8em; margin-right: 0.3em; }
+#readout { font-family: monospace; min-height: 1.2em; }
+";
+
+/// Reads out the cursor position in the units of the data, and hides a series when it is
+/// clicked in the legend.
+///
+/// The bounds and scale of each axis (as drawn) are in the `data-x` and `data-y` attributes of
+/// the chart.
+const SCRIPT: &str = r#"
+const chart = document.getElementById("chart");
+const area = chart.querySelector(".area");
+const readout = document.getElementById("readout");
+function invert(at, scale) {
+  if (scale === "linear") return at;
+  if (scale === "symlog") return Math.sign(at) * (Math.pow(10, Math.abs(at)) - 1);
+  if (scale === "percentile") return 100 * (1 - Math.pow(10, -at));
+  return Math.pow(scale === "log" ? 10 : Number(scale.slice(4)), at);
+}
+function along(axis, fraction) {
+  const [min, max, scale] = chart.dataset[axis].split(" ");
+  const v = invert(Number(min) + fraction * (Number(max) - Number(min)), scale);
+  return Number(v.toPrecision(4)) + (scale === "percentile" ? "%" : "");
+}
+chart.addEventListener("mousemove", (e) => {
+  const box = area.getBoundingClientRect();
+  const fx = (e.clientX - box.left) / box.width;
+  const fy = (box.bottom - e.clientY) / box.height;
+  const inside = fx >= 0 && fx <= 1 && fy >= 0 && fy <= 1;
+  readout.textContent = inside
+    ? `x = ${along("x", fx)}${chart.dataset.xUnit}, y = ${along("y", fy)}${chart.dataset.yUnit}`
+    : "";
+});
+for (const item of document.querySelectorAll(".legend li")) {
+  item.addEventListener("click", () => {
+    item.classList.toggle("off");
+    document.getElementById(item.dataset.series).classList.toggle("off");
+  });
+}
+"#;
+
+/// Writes out a color the way CSS takes it.
+fn css([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Escapes `s` for use in HTML text or (quoted) attributes.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes a self-contained HTML page that charts `data` within the bounds of `frame` as an SVG,
+/// titled by `title`, along with the `raw` points underneath them (faintly) and the
+/// `annotations`.
+///
+/// Every point has a tooltip with its values (and input line, if known), the cursor position is
+/// read out as it moves over the chart, and clicking a series in the legend hides it. `text`, the
+/// plot as drawn for the terminal, goes below the chart for wherever it can't be shown, and the
+/// legend gives the `marks` each series is drawn with there.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write(
+    data: &Data,
+    raw: Option<&Data>,
+    annotations: &[Annotation],
+    frame: &Frame,
+    title: &str,
+    percentile_x: bool,
+    text: &str,
+    marks: &[char],
+    mut out: impl Write,
+) -> eyre::Result<()> {
+    let (left, right, top, bottom) = MARGIN;
+    let (plot_width, plot_height) = (WIDTH - left - right, HEIGHT - top - bottom);
+    let (x_scale, y_scale) = (frame.x_scale(), frame.y_scale());
+    let ((min_x, max_x), (min_y, max_y)) = (frame.x_axis(), frame.y_axis());
+    let to_px = |x: f64| left + (x - min_x) / (max_x - min_x) * plot_width;
+    let to_py = |y: f64| top + (1. - (y - min_y) / (max_y - min_y)) * plot_height;
+    let (x_unit, y_unit) = (&data.x_unit, &data.y_unit);
+    // where a value (in the units of the data) goes, if it is within the frame
+    let px_of = |x: f64| {
+        let x = x_scale.apply(x)?;
+        (min_x..=max_x).contains(&x).then(|| to_px(x))
+    };
+    let py_of = |y: f64| {
+        let y = y_scale.apply(y)?;
+        (min_y..=max_y).contains(&y).then(|| to_py(y))
+    };
+
+    let title = escape(title);
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(
+        out,
+        "<title>{title}</title>\n<style>{STYLE}</style>\n</head>"
+    )?;
+    writeln!(out, "<body>\n<h1>{title}</h1>")?;
+
+    let x_name = if percentile_x {
+        "percentile".to_string()
+    } else {
+        x_scale.name()
+    };
+    writeln!(
+        out,
+        "<svg id=\"chart\" viewBox=\"0 0 {WIDTH} {HEIGHT}\" width=\"{WIDTH}\" height=\"{HEIGHT}\" \
+         data-x=\"{min_x} {max_x} {x_name}\" data-y=\"{min_y} {max_y} {}\" \
+         data-x-unit=\"{}\" data-y-unit=\"{}\">",
+        y_scale.name(),
+        escape(&x_unit.to_string()),
+        escape(&y_unit.to_string()),
+    )?;
+
+    // like in the text plot, bands go underneath everything else
+    for annotation in annotations {
+        if let Annotation::Band { from, to, .. } = *annotation {
+            if let (Some(y0), Some(y1)) = (py_of(to), py_of(from)) {
+                writeln!(
+                    out,
+                    "<rect class=\"band\" x=\"{left}\" y=\"{y0:.1}\" \
+                     width=\"{plot_width}\" height=\"{:.1}\"/>",
+                    y1 - y0
+                )?;
+            }
+        }
+    }
+
+    let x_ticks = if percentile_x {
+        crate::percentile_ticks(frame)
+    } else {
+        x_scale.chart_ticks(min_x, max_x)
+    };
+    for (x, label) in x_ticks {
+        let px = to_px(x);
+        let (y0, y1) = (top, top + plot_height);
+        writeln!(
+            out,
+            "<line class=\"grid\" x1=\"{px:.1}\" y1=\"{y0}\" x2=\"{px:.1}\" y2=\"{y1}\"/>\
+             <text class=\"tick\" x=\"{px:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            y1 + 16.,
+            escape(&label)
+        )?;
+    }
+    for (y, label) in y_scale.chart_ticks(min_y, max_y) {
+        let py = to_py(y);
+        let (x0, x1) = (left, left + plot_width);
+        writeln!(
+            out,
+            "<line class=\"grid\" x1=\"{x0}\" y1=\"{py:.1}\" x2=\"{x1}\" y2=\"{py:.1}\"/>\
+             <text class=\"tick\" x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            x0 - 6.,
+            py + 4.,
+            escape(&label)
+        )?;
+    }
+    writeln!(
+        out,
+        "<rect class=\"area\" x=\"{left}\" y=\"{top}\" \
+         width=\"{plot_width}\" height=\"{plot_height}\"/>"
+    )?;
+
+    // the axis titles read like the header of the text plot, as in "log x (ms)"
+    let axis_title = |scale: Scale, axis: &str, unit: &Unit| {
+        let unit = match unit {
+            Unit::None => String::new(),
+            Unit::Is(unit) => format!(" ({unit})"),
+            Unit::Mixed => " (mixed units)".to_string(),
+        };
+        escape(&format!("{scale}{axis}{unit}"))
+    };
+    let x_title = if percentile_x {
+        "percentile".to_string()
+    } else {
+        axis_title(x_scale, "x", x_unit)
+    };
+    writeln!(
+        out,
+        "<text class=\"title\" x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{x_title}</text>",
+        left + plot_width / 2.,
+        HEIGHT - 10.
+    )?;
+    writeln!(
+        out,
+        "<text class=\"title\" transform=\"translate(15 {:.1}) rotate(-90)\" \
+         text-anchor=\"middle\">{}</text>",
+        top + plot_height / 2.,
+        axis_title(y_scale, "y", y_unit)
+    )?;
+
+    // reference lines go underneath the data
+    let (x0, x1, y0, y1) = (left, left + plot_width, top, top + plot_height);
+    for annotation in annotations {
+        match *annotation {
+            Annotation::HLine { y, .. } => {
+                if let Some(py) = py_of(y) {
+                    writeln!(
+                        out,
+                        "<line class=\"reference\" \
+                         x1=\"{x0}\" y1=\"{py:.1}\" x2=\"{x1}\" y2=\"{py:.1}\"/>"
+                    )?;
+                }
+            }
+            Annotation::VLine { x, .. } => {
+                if let Some(px) = px_of(x) {
+                    writeln!(
+                        out,
+                        "<line class=\"reference\" \
+                         x1=\"{px:.1}\" y1=\"{y0}\" x2=\"{px:.1}\" y2=\"{y1}\"/>"
+                    )?;
+                }
+            }
+            Annotation::Band { .. } => {}
+        }
+    }
+
+    if let Some(raw) = raw {
+        for (column, ys) in raw.ys.iter().enumerate() {
+            let color = css(COLORS[column % COLORS.len()]);
+            writeln!(out, "<g class=\"raw\" fill=\"{color}\">")?;
+            for (&x, &y) in raw.xs.iter().zip(ys) {
+                if let Some((px, py)) = px_of(x).zip(py_of(y)) {
+                    writeln!(out, "<circle cx=\"{px:.1}\" cy=\"{py:.1}\" r=\"2\"/>")?;
+                }
+            }
+            writeln!(out, "</g>")?;
+        }
+    }
+
+    for (column, ys) in data.ys.iter().enumerate() {
+        let color = css(COLORS[column % COLORS.len()]);
+        let label = escape(&data.label(column));
+        writeln!(
+            out,
+            "<g class=\"series\" id=\"s{column}\" fill=\"{color}\">"
+        )?;
+        for (row, (&x, &y)) in data.xs.iter().zip(ys).enumerate() {
+            if !x.is_finite() || !y.is_finite() {
+                continue;
+            }
+            // points that can't be drawn on the scales were counted as dropped by the frame
+            let Some((px, py)) = x_scale.apply(x).zip(y_scale.apply(y)) else {
+                continue;
+            };
+            let x = if percentile_x {
+                let p = (crate::transform::percentile_of_x(x) * 1e6).round() / 1e6;
+                format!("{p}%")
+            } else {
+                format!("{x}{}", escape(&x_unit.to_string()))
+            };
+            let y = format!("{y}{}", escape(&y_unit.to_string()));
+            let line = match data.lines.get(row) {
+                Some(line) => format!(" (line {line})"),
+                None => String::new(),
+            };
+            writeln!(
+                out,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"2.5\">\
+                 <title>{label}: x = {x}, y = {y}{line}</title></circle>",
+                to_px(px),
+                to_py(py)
+            )?;
+        }
+        writeln!(out, "</g>")?;
+    }
+
+    // labels go on top of everything, so that they can always be read
+    for annotation in annotations {
+        let (x, y, anchor, label) = match annotation {
+            // right-aligned just above the line, or the top edge of the band
+            Annotation::HLine {
+                y,
+                label: Some(label),
+            }
+            | Annotation::Band {
+                to: y,
+                label: Some(label),
+                ..
+            } => {
+                let Some(py) = py_of(*y) else { continue };
+                (x1 - 4., py - 4., "end", label)
+            }
+            // along the top, to the right of the line
+            Annotation::VLine {
+                x,
+                label: Some(label),
+            } => {
+                let Some(px) = px_of(*x) else { continue };
+                (px + 4., y0 + 12., "start", label)
+            }
+            _ => continue,
+        };
+        writeln!(
+            out,
+            "<text class=\"label\" x=\"{x:.1}\" y=\"{y:.1}\" text-anchor=\"{anchor}\">{}</text>",
+            escape(label)
+        )?;
+    }
+    writeln!(out, "</svg>")?;
+
+    writeln!(out, "<ul class=\"legend\">")?;
+    for column in 0..data.ys.len() {
+        writeln!(
+            out,
+            "<li data-series=\"s{column}\"><span style=\"background: {}\"></span>{} ({})</li>",
+            css(COLORS[column % COLORS.len()]),
+            escape(&data.label(column)),
+            escape(&marks[column].to_string())
+        )?;
+    }
+    writeln!(out, "</ul>")?;
+    writeln!(out, "<p id=\"readout\"></p>")?;
+    writeln!(
+        out,
+        "<details>\n<summary>As text</summary>\n<pre>{}</pre>\n</details>",
+        escape(text)
+    )?;
+    writeln!(out, "<script>{SCRIPT}</script>\n</body>\n</html>")?;
+    Ok(())
+}
@@ -0,0 +1,309 @@
+use crate::annotate::{self, Annotation};
+use crate::canvas::Charset;
+use crate::fit::significant;
+use crate::scale::Scale;
+use crate::{Canvas, Data, Frame, Mode, PAD};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Stylize;
+use crossterm::{cursor, execute, queue, terminal};
+use std::borrow::Cow;
+use std::io::{IsTerminal, Write};
+
+/// Rows of the terminal that are not available to the canvas: the header line above it, the blank
+/// line and X tick labels below it, the cursor readout, and the key help.
+const RESERVED_ROWS: usize = 5;
+
+/// How much one press of `+` narrows the view (and `-` widens it).
+const ZOOM: f64 = 0.5;
+
+/// How far one press of `H`, `J`, `K`, or `L` moves the view, as a fraction of it.
+const PAN: f64 = 0.25;
+
+const HELP: &str = "arrows/hjkl: move  +/-: zoom  HJKL: pan  r: reset  0-9: series  \
+                    m: mode  x/y: log  c: cdf  q: quit";
+
+/// A plot to explore in the terminal, rather than print once.
+///
+/// Everything is re-drawn from the data as it was read after every key, so nothing is read twice.
+pub(crate) struct Viewer {
+    pub(crate) data: Data,
+    /// The raw points underneath smoothed ones, if they are to be shown.
+    pub(crate) raw: Option<Data>,
+    pub(crate) annotations: Vec<Annotation>,
+    /// The scales of the X and Y axes as drawn (so for a CDF, the X axis holds the values).
+    pub(crate) scales: (Scale, Scale),
+    pub(crate) mode: Mode,
+    pub(crate) charset: Charset,
+    pub(crate) cdf: bool,
+    /// Whether the CDF (when toggled on) is the complementary one.
+    pub(crate) ccdf: bool,
+    /// Whether X is a percentile, as made by `--percentile-plot`.
+    pub(crate) percentile_x: bool,
+}
+
+/// What can be changed from one key to the next.
+struct View {
+    mode: Mode,
+    scales: (Scale, Scale),
+    cdf: bool,
+    /// Whether each Y column is left out of the plot.
+    hidden: Vec<bool>,
+    /// The bounds zoomed or panned to, if any.
+    bounds: Option<Frame>,
+    /// The cell (as row, column) under the crosshair.
+    cursor: (usize, usize),
+}
+
+/// Puts the terminal into raw mode on an alternate screen for as long as this lives.
+///
+/// Restoring the terminal on drop means it also happens if drawing fails part-way.
+struct RawScreen;
+
+impl RawScreen {
+    fn enter() -> std::io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let screen = RawScreen;
+        execute!(
+            std::io::stdout(),
+            terminal::EnterAlternateScreen,
+            cursor::Hide
+        )?;
+        Ok(screen)
+    }
+}
+
+impl Drop for RawScreen {
+    fn drop(&mut self) {
+        let _ = execute!(
+            std::io::stdout(),
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl Viewer {
+    /// Shows the plot and handles keys until told to quit.
+    pub(crate) fn run(self) -> eyre::Result<()> {
+        eyre::ensure!(
+            std::io::stdout().is_terminal(),
+            "--interactive needs a terminal to draw to"
+        );
+
+        // the scale each axis switches to when toggled off linear
+        let log_or = |scale: Scale| {
+            if scale.is_linear() {
+                Scale::Log(10.)
+            } else {
+                scale
+            }
+        };
+        let logs = (log_or(self.scales.0), log_or(self.scales.1));
+
+        let size = || -> std::io::Result<(usize, usize)> {
+            let (columns, rows) = terminal::size()?;
+            let width = usize::from(columns).max(PAD + 1);
+            let height = usize::from(rows).saturating_sub(RESERVED_ROWS).max(PAD + 1);
+            Ok((width, height))
+        };
+        let (width, height) = size()?;
+        let mut view = View {
+            mode: self.mode,
+            scales: self.scales,
+            cdf: self.cdf,
+            hidden: vec![false; self.data.ys.len()],
+            bounds: None,
+            cursor: (height / 2, width / 2),
+        };
+
+        let _screen = RawScreen::enter()?;
+        let mut stdout = std::io::stdout().lock();
+        loop {
+            let (width, height) = size()?;
+            view.cursor = (view.cursor.0.min(height - 1), view.cursor.1.min(width - 1));
+
+            let frame = self.show(&view, (width, height), &mut stdout)?;
+
+            let Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            }) = event::read()?
+            else {
+                // including resizes, which the next time around picks up
+                continue;
+            };
+            let (row, column) = view.cursor;
+            match code {
+                KeyCode::Char('q') | KeyCode::Esc => break,
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => break,
+                KeyCode::Left | KeyCode::Char('h') => view.cursor.1 = column.saturating_sub(1),
+                KeyCode::Right | KeyCode::Char('l') => view.cursor.1 = column + 1,
+                KeyCode::Up | KeyCode::Char('k') => view.cursor.0 = row.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => view.cursor.0 = row + 1,
+                KeyCode::Char('+' | '=') => view.bounds = Some(frame.zoomed(view.cursor, ZOOM)),
+                KeyCode::Char('-' | '_') => {
+                    view.bounds = Some(frame.zoomed(view.cursor, 1. / ZOOM));
+                }
+                KeyCode::Char('H') => view.bounds = Some(frame.panned((-PAN, 0.))),
+                KeyCode::Char('L') => view.bounds = Some(frame.panned((PAN, 0.))),
+                KeyCode::Char('K') => view.bounds = Some(frame.panned((0., PAN))),
+                KeyCode::Char('J') => view.bounds = Some(frame.panned((0., -PAN))),
+                KeyCode::Char('r') => view.bounds = None,
+                KeyCode::Char(c @ '0'..='9') => {
+                    let column = usize::from(c as u8 - b'0');
+                    if let Some(hidden) = view.hidden.get_mut(column) {
+                        *hidden = !*hidden;
+                    }
+                }
+                KeyCode::Char('m') => {
+                    view.mode = match view.mode {
+                        Mode::Dot => Mode::Count,
+                        Mode::Count => Mode::Dot,
+                    };
+                }
+                // the bounds of the view are along the axes as drawn, so they don't carry over to
+                // a different scale or to a CDF.
+                KeyCode::Char('x') if !self.percentile_x => {
+                    let (x, y) = view.scales;
+                    view.scales = (if x.is_linear() { logs.0 } else { Scale::Linear }, y);
+                    view.bounds = None;
+                }
+                KeyCode::Char('y') => {
+                    let (x, y) = view.scales;
+                    view.scales = (x, if y.is_linear() { logs.1 } else { Scale::Linear });
+                    view.bounds = None;
+                }
+                KeyCode::Char('c') if !self.percentile_x => {
+                    view.cdf = !view.cdf;
+                    view.bounds = None;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the plot as `view` has it onto the (cleared) screen, and returns the frame it used.
+    fn show(
+        &self,
+        view: &View,
+        (width, height): (usize, usize),
+        mut out: impl Write,
+    ) -> eyre::Result<Frame> {
+        // hidden columns are left in (as missing values) so every column keeps its mark
+        let hide = |data: &Data| {
+            let mut data = data.clone();
+            for (ys, _) in data.ys.iter_mut().zip(&view.hidden).filter(|(_, &h)| h) {
+                ys.fill(f64::NAN);
+            }
+            data
+        };
+        let any_hidden = view.hidden.contains(&true);
+        let mut data = Cow::Borrowed(&self.data);
+        let mut raw = self.raw.as_ref().map(Cow::Borrowed);
+        if any_hidden {
+            data = Cow::Owned(hide(&data));
+            raw = raw.map(|raw| Cow::Owned(hide(&raw)));
+        }
+        if view.cdf {
+            data = Cow::Owned(crate::cdf_of(
+                &data,
+                (width, height),
+                self.ccdf,
+                view.scales,
+            ));
+            raw = None;
+        }
+
+        let (x_scale, y_scale) = view.scales;
+        let (annotation_xs, annotation_ys) = annotate::extent(&self.annotations);
+        let bounded_by = raw.as_deref().unwrap_or(&data);
+        let mut frame = Frame::new_scaled_over(width, height, bounded_by, x_scale, y_scale)
+            .including(&annotation_xs, &annotation_ys);
+        if let Some(bounds) = &view.bounds {
+            frame = frame.viewing(bounds);
+        }
+
+        let mut canvas = Canvas::new(height, width, view.mode, self.charset);
+        for annotation in &self.annotations {
+            annotation.shade_into(&mut canvas, &frame);
+        }
+        frame.draw_into(&mut canvas);
+        for annotation in &self.annotations {
+            annotation.draw_into(&mut canvas, &frame);
+        }
+        if let Some(raw) = &raw {
+            raw.draw_faint_into(&mut canvas, &frame);
+        }
+        data.draw_into(&mut canvas, &frame);
+        for annotation in &self.annotations {
+            annotation.label_into(&mut canvas, &frame);
+        }
+
+        let mut plot = Vec::new();
+        let percentile_x = self.percentile_x && !view.cdf;
+        crate::render(
+            &data,
+            &frame,
+            &canvas,
+            &[],
+            (&[], 0),
+            percentile_x,
+            &mut plot,
+        )?;
+        let plot = String::from_utf8(plot).expect("plots are UTF-8");
+
+        queue!(
+            out,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        let (cursor_row, cursor_column) = view.cursor;
+        for (i, line) in plot.lines().enumerate() {
+            let Some(row) = i.checked_sub(1).filter(|&row| row < height) else {
+                write!(out, "{line}\r\n")?;
+                continue;
+            };
+            // the crosshair goes on top of the plot as printed, so it never ends up in the canvas.
+            for (column, c) in line.chars().enumerate() {
+                if (row, column) == view.cursor {
+                    write!(out, "{}", c.reverse())?;
+                } else if c == ' ' && (row == cursor_row || column == cursor_column) {
+                    write!(out, "{}", '.'.dim())?;
+                } else {
+                    write!(out, "{c}")?;
+                }
+            }
+            write!(out, "\r\n")?;
+        }
+
+        let (x, y) = (frame.column_to_x(cursor_column), frame.row_to_y(cursor_row));
+        let y = significant(y);
+        let (x_unit, y_unit) = (&data.x_unit, &data.y_unit);
+        let mut readout = if percentile_x {
+            let p = (crate::transform::percentile_of_x(x) * 1e6).round() / 1e6;
+            format!("    cursor: {p}%, y = {y}{y_unit}")
+        } else {
+            format!(
+                "    cursor: x = {}{x_unit}, y = {y}{y_unit}",
+                significant(x)
+            )
+        };
+        let hidden: Vec<_> = (0..view.hidden.len())
+            .filter(|&c| view.hidden[c])
+            .map(|c| data.label(c))
+            .collect();
+        if !hidden.is_empty() {
+            readout.push_str(&format!("  (hiding {})", hidden.join(", ")));
+        }
+        let readout: String = readout.chars().take(width).collect();
+        let help: String = format!("    {HELP}").chars().take(width).collect();
+        write!(out, "{readout}\r\n{help}")?;
+        out.flush()?;
+
+        Ok(frame)
+    }
+}
@@ -0,0 +1,545 @@
+//! The implementation of the `dings` binary.
+//!
+//! [`run`] is the only real entry point; [`fuzz`] exists only for the targets in `fuzz/`.
+
+use args::Opt;
+use canvas::{Canvas, Charset, Mode};
+use data::{Data, MARKS};
+use eyre::Context;
+use fit::Model;
+use frame::{Frame, PAD};
+use outliers::Outlier;
+use output::Output;
+use parse::{Format, Reader};
+use reduce::Reducer;
+use scale::Scale;
+use std::io::{IsTerminal, Write};
+use transform::Delta;
+
+mod annotate;
+mod args;
+mod canvas;
+mod config;
+mod data;
+mod emit;
+mod fit;
+mod follow;
+mod font;
+mod frame;
+mod graphics;
+mod grid;
+mod hlog;
+mod html;
+mod interactive;
+mod outliers;
+mod output;
+mod parse;
+mod png;
+mod raster;
+mod reduce;
+mod scale;
+mod term;
+mod transform;
+
+/// Reads data from stdin and plots it to stdout according to the command-line arguments.
+pub fn run() -> eyre::Result<()> {
+    let Some(opt) = Opt::parse_from_env().context("parse command-line arguments")? else {
+        // help text was displayed
+        return Ok(());
+    };
+
+    if let Some(path) = &opt.follow {
+        return follow::run(path, &opt).with_context(|| format!("follow {}", path.display()));
+    }
+
+    // checked before reading any input, which may take a while (or be typed in)
+    eyre::ensure!(
+        opt.output != Output::Png || !std::io::stdout().is_terminal(),
+        "--output png writes an image, so redirect it to a file"
+    );
+
+    let (width, height) = opt.size();
+    let stdin = std::io::stdin();
+    let mut stdin = stdin.lock();
+    let data = match opt.format {
+        Format::Text => {
+            let data = Data::read_from(
+                Reader::new(&mut stdin, opt.parse),
+                opt.x_is_row,
+                opt.reduce
+                    .then(|| Reducer::new(width, height, (opt.x_scale, opt.y_scale))),
+            )
+            .context("read input")?;
+            transform_rows(data, &opt, width)?
+        }
+        Format::Hlog => {
+            let log = hlog::Log::read_from(&mut stdin).context("read interval log")?;
+            if let Some(quantiles) = &opt.quantiles {
+                log.quantiles(quantiles)
+                    .context("compute quantiles of intervals")?
+            } else {
+                log.percentile_plot()
+                    .context("compute distribution of intervals")?
+            }
+        }
+    };
+
+    let stdout = std::io::stdout();
+    let stdout = stdout.lock();
+    plot(data, &opt, (width, height), stdout)
+}
+
+/// Applies the transformations given on the command line that turn the rows of (text) input into
+/// the points to plot on a canvas `width` wide.
+fn transform_rows(mut data: Data, opt: &Opt, width: usize) -> eyre::Result<Data> {
+    if let Some(delta) = opt.delta {
+        for ys in &mut data.ys {
+            delta.apply(&data.xs, ys);
+        }
+        if delta == Delta::Rate {
+            data.y_unit = data.y_unit.per(&data.x_unit);
+        }
+    }
+
+    if let Some(quantiles) = &opt.quantiles {
+        data = quantiles.apply(&data, width).context("compute quantiles")?;
+    }
+    if opt.percentile_plot {
+        data = transform::percentile_plot(&data);
+    }
+    Ok(data)
+}
+
+/// Plots `data` to `out` at the given size (see [`Opt::size`]), according to the command-line
+/// arguments.
+fn plot(
+    mut data: Data,
+    opt: &Opt,
+    (width, height): (usize, usize),
+    out: impl Write,
+) -> eyre::Result<()> {
+    let &Opt {
+        x_scale,
+        y_scale,
+        mode,
+        charset,
+        cdf,
+        ccdf,
+        draw_axes,
+        grid,
+        independent_axes,
+        smooth,
+        show_raw,
+        percentile_plot,
+        fit,
+        ref annotations,
+        outliers,
+        top_outliers,
+        interactive,
+        emit,
+        output,
+        ..
+    } = opt;
+
+    // if the raw points are to be drawn underneath the smoothed ones, we need to hold on to them.
+    let mut raw = None;
+    if let Some(smooth) = smooth {
+        if show_raw {
+            raw = Some(data.clone());
+        }
+        for ys in &mut data.ys {
+            smooth.apply(ys);
+        }
+    }
+
+    // with --ccdf, the scales apply to the axes as drawn, whereas with --cdf, log y is
+    // interpreted as log of the _input_ not _output_, and the input ends up along X.
+    let (x_scale, y_scale) = if cdf && !ccdf {
+        (y_scale, Scale::Linear)
+    } else {
+        (x_scale, y_scale)
+    };
+    if interactive {
+        let viewer = interactive::Viewer {
+            data,
+            raw,
+            annotations: annotations.clone(),
+            scales: (x_scale, y_scale),
+            mode,
+            charset,
+            cdf,
+            ccdf,
+            percentile_x: percentile_plot,
+        };
+        return viewer.run().context("run interactive viewer");
+    }
+    if cdf {
+        data = cdf_of(&data, (width, height), ccdf, (x_scale, y_scale));
+    }
+
+    // NOTE: smoothing never moves a point outside the range of the raw points
+    let bounded_by = raw.as_ref().unwrap_or(&data);
+    // reference lines and bands are meant to be seen, even if the data doesn't reach them.
+    let (annotation_xs, annotation_ys) = annotate::extent(annotations);
+    let frame = Frame::new_scaled_over(width, height, bounded_by, x_scale, y_scale)
+        .including(&annotation_xs, &annotation_ys);
+
+    if let Some(emit) = emit {
+        return emit
+            .write(&data, &frame, mode, out)
+            .context("write plot data");
+    }
+    if output == Output::Png {
+        let raster = raster::plot(
+            &data,
+            raw.as_ref(),
+            annotations,
+            &frame,
+            percentile_plot,
+            draw_axes,
+            (width, height),
+        );
+        return png::write(&raster, out).context("write png");
+    }
+    if let Output::Inline(protocol) = output {
+        if protocol.supported() {
+            let size = graphics::pixels_of((width, height));
+            let raster = raster::plot(
+                &data,
+                raw.as_ref(),
+                annotations,
+                &frame,
+                percentile_plot,
+                draw_axes,
+                size,
+            );
+            return protocol
+                .write(&raster, (width, height), out)
+                .context("write inline image");
+        }
+        // the terminal can't show it, so it gets the text plot instead
+    }
+
+    let fits: Vec<_> = match fit {
+        Some(fit) => (0..data.ys.len())
+            .map(|column| fit.to(&data.xs, &data.ys[column]))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let outliers: Vec<_> = match outliers {
+        Some(outliers) => outliers.find(&data),
+        None => Vec::new(),
+    };
+
+    let canvas = if let Some(grid) = grid {
+        grid::draw(
+            &data,
+            raw.as_ref(),
+            &fits,
+            annotations,
+            &outliers,
+            grid,
+            independent_axes,
+            draw_axes,
+            (x_scale, y_scale),
+            (width, height),
+            (mode, charset),
+        )
+        .context("lay out grid")?
+    } else {
+        let mut canvas = Canvas::new(height, width, mode, charset);
+        for annotation in annotations {
+            annotation.shade_into(&mut canvas, &frame);
+        }
+        // if -A is passed, we don't draw axes.
+        if draw_axes {
+            frame.draw_into(&mut canvas);
+        }
+        for annotation in annotations {
+            annotation.draw_into(&mut canvas, &frame);
+        }
+        if let Some(raw) = &raw {
+            raw.draw_faint_into(&mut canvas, &frame);
+        }
+        for model in fits.iter().flatten() {
+            model.draw_into(&mut canvas, &frame);
+        }
+        data.draw_into(&mut canvas, &frame);
+        for outlier in &outliers {
+            outlier.draw_into(&data, &mut canvas, &frame);
+        }
+        for annotation in annotations {
+            annotation.label_into(&mut canvas, &frame);
+        }
+        canvas
+    };
+
+    let listed = &outliers[..top_outliers.min(outliers.len())];
+    match output {
+        Output::Text | Output::Inline(_) => render(
+            &data,
+            &frame,
+            &canvas,
+            &fits,
+            (listed, outliers.len()),
+            percentile_plot,
+            out,
+        )
+        .context("render output")?,
+        Output::Html => {
+            let mut text = Vec::new();
+            render(
+                &data,
+                &frame,
+                &canvas,
+                &fits,
+                (listed, outliers.len()),
+                percentile_plot,
+                &mut text,
+            )
+            .context("render output")?;
+            let text = String::from_utf8(text).expect("plots are UTF-8");
+            let marks: Vec<_> = (0..data.ys.len()).map(|c| canvas.mark(c)).collect();
+            let title = match (cdf, ccdf, percentile_plot) {
+                (true, true, _) => "dings: CCDF",
+                (true, false, _) => "dings: CDF",
+                (false, _, true) => "dings: percentiles",
+                (false, _, false) => "dings",
+            };
+            html::write(
+                &data,
+                raw.as_ref(),
+                annotations,
+                &frame,
+                title,
+                percentile_plot,
+                &text,
+                &marks,
+                out,
+            )
+            .context("write html")?;
+        }
+        Output::Png => unreachable!("images are drawn from the frame, not the canvas"),
+    }
+
+    Ok(())
+}
+
+/// Computes the (complementary, if `ccdf`) CDF of each Y column of `data`, to be drawn at the
+/// given size on axes with the given scales.
+fn cdf_of(data: &Data, size: (usize, usize), ccdf: bool, scales: (Scale, Scale)) -> Data {
+    let mut cdf = transform::cdf(data, size, ccdf, scales);
+    if ccdf && scales.1.apply(0.).is_none() {
+        // nothing is greater than the largest value, and that's not worth reporting as dropped
+        for y in cdf.ys.iter_mut().flatten() {
+            if *y == 0. {
+                *y = f64::NAN;
+            }
+        }
+    }
+    cdf
+}
+
+/// Describes the range (and scale) of each axis of `frame`, as at the top of a plot.
+fn ranges(data: &Data, frame: &Frame, percentile_x: bool) -> String {
+    let (min_x, max_x) = frame.x_bounds();
+    let (min_y, max_y) = frame.y_bounds();
+    let (x_unit, y_unit) = (&data.x_unit, &data.y_unit);
+    let (x_scale, y_scale) = (frame.x_scale(), frame.y_scale());
+    let (dropped_x, dropped_y) = frame.dropped();
+    let dropped = |n: usize| {
+        if n == 0 {
+            String::new()
+        } else {
+            format!(" ({n} dropped)")
+        }
+    };
+    let x = if percentile_x {
+        // rounded, since the percentiles come out of a log scale and so aren't quite exact
+        let percentile = |x| (transform::percentile_of_x(x) * 1e6).round() / 1e6;
+        let (min_p, max_p) = (percentile(min_x), percentile(max_x));
+        format!("    percentile x: [{min_p}% - {max_p}%]")
+    } else {
+        let dropped_x = dropped(dropped_x);
+        format!("    {x_scale}x: [{min_x} - {max_x}]{x_unit}{dropped_x}")
+    };
+    let dropped_y = dropped(dropped_y);
+    format!("{x}    {y_scale}y: [{min_y} - {max_y}]{y_unit}{dropped_y}")
+}
+
+fn render(
+    data: &Data,
+    frame: &Frame,
+    canvas: &Canvas,
+    fits: &[Option<Model>],
+    (listed, outliers): (&[Outlier], usize),
+    percentile_x: bool,
+    mut out: impl Write,
+) -> eyre::Result<()> {
+    write!(out, "{}", ranges(data, frame, percentile_x))?;
+    if let Mode::Dot = canvas.mode {
+        write!(out, " -- ")?;
+        #[allow(clippy::needless_range_loop)]
+        for column in 0..data.ys.len() {
+            write!(
+                out,
+                "{}{}: {}",
+                if column > 0 { ", " } else { "" },
+                data.label(column),
+                canvas.mark(column)
+            )?;
+        }
+        if outliers > 0 {
+            write!(out, ", outliers: {}", data::OUTLIER_MARK)?;
+        }
+    }
+    writeln!(out)?;
+    write!(out, "{canvas}")?;
+    if percentile_x {
+        writeln!(out)?;
+        write_x_ticks(frame, canvas, percentile_ticks(frame), &mut out)?;
+    } else if !frame.x_scale().is_linear() {
+        writeln!(out)?;
+        write_x_ticks(frame, canvas, frame.x_ticks(), &mut out)?;
+    }
+    writeln!(out)?;
+    for (column, model) in fits.iter().enumerate() {
+        let (label, mark) = (data.label(column), canvas.mark(column));
+        match model {
+            Some(model) => {
+                let r_squared = model.r_squared();
+                writeln!(out, "    {label} ({mark}): {model}, R² = {r_squared:.4}")?;
+            }
+            None => writeln!(out, "    {label} ({mark}): too few points to fit")?,
+        }
+    }
+    if !listed.is_empty() {
+        writeln!(
+            out,
+            "    {} most extreme of {outliers} outliers:",
+            listed.len()
+        )?;
+    }
+    let (x_unit, y_unit) = (&data.x_unit, &data.y_unit);
+    for outlier in listed {
+        let (label, mark) = (data.label(outlier.column), canvas.mark(outlier.column));
+        let (x, y) = (data.xs[outlier.row], data.ys[outlier.column][outlier.row]);
+        let line = data.lines[outlier.row];
+        writeln!(
+            out,
+            "    line {line}: {label} ({mark}) y = {y}{y_unit} at x = {x}{x_unit}"
+        )?;
+    }
+    Ok(())
+}
+
+/// The ticks along the X axis of a percentile plot, labelled with the percentile at each.
+fn percentile_ticks(frame: &Frame) -> Vec<(f64, String)> {
+    let (_, max_x) = frame.x_bounds();
+    // 50% is at log10(2), and then every nine is one more along X
+    let ticks = std::iter::once(2f64.log10()).chain((1..).map(f64::from));
+    ticks
+        .take_while(|&x| x <= max_x)
+        .map(|x| {
+            let label = format!("{}%", (transform::percentile_of_x(x) * 1e6).round() / 1e6);
+            (x, label)
+        })
+        .collect()
+}
+
+/// Writes a line with the label of each tick (at a position along the X axis as drawn) under the
+/// column it falls on, leaving out any that would overlap.
+fn write_x_ticks(
+    frame: &Frame,
+    canvas: &Canvas,
+    ticks: impl IntoIterator<Item = (f64, String)>,
+    mut out: impl Write,
+) -> eyre::Result<()> {
+    let mut line = vec![b' '; canvas.columns()];
+    let mut free_from = 0;
+    for (x, label) in ticks {
+        // labels that would run off the right edge are pulled back to end there instead
+        let column = frame
+            .x_to_column(x)
+            .min(line.len().saturating_sub(label.len()));
+        if column < free_from || column + label.len() > line.len() {
+            continue;
+        }
+        line[column..column + label.len()].copy_from_slice(label.as_bytes());
+        free_from = column + label.len() + 1;
+    }
+    let line = String::from_utf8(line).expect("labels are ASCII");
+    write!(out, "{}", line.trim_end())?;
+    Ok(())
+}
+
+/// Entry points for the fuzz targets in `fuzz/`.
+///
+/// These are not a stable API, and may change at any time.
+#[doc(hidden)]
+pub mod fuzz {
+    use crate::parse::{ParseOptions, Reader};
+    use crate::reduce::Reducer;
+    use crate::{Canvas, Charset, Data, Frame, Mode, Scale, MARKS, PAD};
+
+    /// Parses `input` the way stdin is parsed, and plots the result.
+    pub fn parse_and_draw(
+        input: &[u8],
+        x_is_row: bool,
+        reduce: bool,
+        strict: bool,
+        thousands: bool,
+        decimal_comma: bool,
+        units: bool,
+    ) {
+        let (width, height) = (72, 40);
+        let reducer = reduce.then(|| Reducer::new(width, height, (Scale::Linear, Scale::Linear)));
+        let options = ParseOptions {
+            strict,
+            thousands,
+            decimal_comma,
+            units,
+        };
+        // errors (like invalid UTF-8) are fine, it's panics we're after
+        let Ok(data) = Data::read_from(Reader::new(input, options), x_is_row, reducer) else {
+            return;
+        };
+        draw(&data, width, height, Mode::Dot);
+        draw(&data, width, height, Mode::Count);
+    }
+
+    /// Plots the given data onto a canvas of (roughly) the given size.
+    ///
+    /// The data is first massaged into a shape the parser could have produced: every Y column is
+    /// as long as `xs`, and there are no more columns than there are marks.
+    pub fn draw_data(xs: Vec<f64>, mut ys: Vec<Vec<f64>>, width: u8, height: u8, count: bool) {
+        ys.truncate(MARKS.len());
+        for column in &mut ys {
+            column.resize(xs.len(), f64::NAN);
+        }
+        let data = Data {
+            xs,
+            ys,
+            ..Data::default()
+        };
+        let width = PAD + 1 + usize::from(width);
+        let height = PAD + 1 + usize::from(height);
+        draw(
+            &data,
+            width,
+            height,
+            if count { Mode::Count } else { Mode::Dot },
+        );
+    }
+
+    fn draw(data: &Data, width: usize, height: usize, mode: Mode) {
+        let frame = Frame::new_over(width, height, data);
+        for charset in [Charset::Ascii, Charset::Unicode] {
+            let mut canvas = Canvas::new(height, width, mode, charset);
+            frame.draw_into(&mut canvas);
+            data.draw_into(&mut canvas, &frame);
+            let _ = canvas.to_string();
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::canvas::Cell;
+use crate::data::OUTLIER_MARK;
+use crate::{Canvas, Data, Frame};
+use std::str::FromStr;
+
+/// How to tell which points of a Y column are outliers.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Outliers {
+    /// Tukey's fences: more than 1.5 interquartile ranges below the first or above the third
+    /// quartile.
+    Iqr,
+    /// More than the given number of standard deviations from the mean.
+    ZScore(f64),
+}
+
+impl FromStr for Outliers {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "iqr" {
+            return Ok(Outliers::Iqr);
+        }
+        let Some(n) = s.strip_prefix("zscore:") else {
+            eyre::bail!("unknown outlier test '{s}'; expected iqr or zscore:N");
+        };
+        let n: f64 = n
+            .parse()
+            .map_err(|e| eyre::eyre!("invalid z-score '{n}': {e}"))?;
+        eyre::ensure!(n > 0. && n.is_finite(), "z-score must be positive, got {n}");
+        Ok(Outliers::ZScore(n))
+    }
+}
+
+/// A point flagged by [`Outliers::find`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Outlier {
+    pub(crate) row: usize,
+    pub(crate) column: usize,
+    /// How far out the point is, in interquartile ranges past the quartile or in standard
+    /// deviations from the mean. Bigger is more extreme.
+    pub(crate) score: f64,
+}
+
+impl Outliers {
+    /// Flags the outliers in every Y column of `data`, most extreme first.
+    pub(crate) fn find(self, data: &Data) -> Vec<Outlier> {
+        let mut outliers = Vec::new();
+        for (column, ys) in data.ys.iter().enumerate() {
+            // NOTE: a point with no X isn't drawn, so it can't be pointed out either
+            let values: Vec<_> = ys
+                .iter()
+                .zip(&data.xs)
+                .filter(|(y, x)| y.is_finite() && x.is_finite())
+                .map(|(&y, _)| y)
+                .collect();
+            let Some(score) = self.scorer(values) else {
+                continue;
+            };
+            for (row, (&y, x)) in ys.iter().zip(&data.xs).enumerate() {
+                if !y.is_finite() || !x.is_finite() {
+                    continue;
+                }
+                if let Some(score) = score(y) {
+                    outliers.push(Outlier { row, column, score });
+                }
+            }
+        }
+        outliers.sort_by(|a, b| b.score.total_cmp(&a.score));
+        outliers
+    }
+
+    /// Returns a function that gives the score of a value if it is an outlier among `values`,
+    /// or `None` if there are too few values to tell.
+    fn scorer(self, mut values: Vec<f64>) -> Option<Box<dyn Fn(f64) -> Option<f64>>> {
+        if values.len() < 2 {
+            return None;
+        }
+        match self {
+            Outliers::Iqr => {
+                values.sort_by(f64::total_cmp);
+                let (q1, q3) = (quantile(&values, 0.25), quantile(&values, 0.75));
+                let iqr = q3 - q1;
+                Some(Box::new(move |y| {
+                    let beyond = if y > q3 { y - q3 } else { q1 - y };
+                    // NOTE: with an IQR of 0, anything off the quartiles is (infinitely) far out
+                    (beyond > 1.5 * iqr).then(|| beyond / iqr)
+                }))
+            }
+            Outliers::ZScore(n) => {
+                let mean = values.iter().sum::<f64>() / values.len() as f64;
+                let variance =
+                    values.iter().map(|y| (y - mean).powi(2)).sum::<f64>() / values.len() as f64;
+                let sd = variance.sqrt();
+                if sd == 0. {
+                    return None;
+                }
+                Some(Box::new(move |y| {
+                    let z = (y - mean).abs() / sd;
+                    (z > n).then_some(z)
+                }))
+            }
+        }
+    }
+}
+
+/// The `q` quantile of the (sorted, non-empty) `values`, interpolating between neighbours.
+fn quantile(values: &[f64], q: f64) -> f64 {
+    let at = q * (values.len() - 1) as f64;
+    let (below, above) = (at.floor() as usize, at.ceil() as usize);
+    values[below] + (values[above] - values[below]) * (at - below as f64)
+}
+
+impl Outlier {
+    /// Draws the point over whatever was there with [`OUTLIER_MARK`].
+    ///
+    /// This should be drawn after the data, so it isn't hidden (or counted).
+    pub(crate) fn draw_into(&self, data: &Data, canvas: &mut Canvas, using: &Frame) {
+        let (x, y) = (data.xs[self.row], data.ys[self.column][self.row]);
+        let Some((row, column)) = using.point_to_cell((x, y)) else {
+            return;
+        };
+        if let Some(cell) = canvas.cell(row, column) {
+            *cell = Cell::Glyph(OUTLIER_MARK);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(ys: &[f64]) -> Data {
+        Data {
+            xs: (0..ys.len()).map(|x| x as f64).collect(),
+            ys: vec![ys.to_vec()],
+            ..Data::default()
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("iqr".parse::<Outliers>().unwrap(), Outliers::Iqr);
+        assert_eq!(
+            "zscore:3".parse::<Outliers>().unwrap(),
+            Outliers::ZScore(3.)
+        );
+        assert!("zscore:-1".parse::<Outliers>().is_err());
+        assert!("zscore".parse::<Outliers>().is_err());
+        assert!("mad".parse::<Outliers>().is_err());
+    }
+
+    #[test]
+    fn iqr() {
+        let outliers = Outliers::Iqr.find(&data(&[5., 6., 5., -20., 7., 6., 40., 5., f64::NAN]));
+        let rows: Vec<_> = outliers.iter().map(|o| o.row).collect();
+        assert_eq!(rows, [6, 3]);
+    }
+
+    #[test]
+    fn iqr_of_flat_series() {
+        let outliers = Outliers::Iqr.find(&data(&[1., 1., 1., 1., 9., 1.]));
+        let rows: Vec<_> = outliers.iter().map(|o| o.row).collect();
+        assert_eq!(rows, [4]);
+    }
+
+    #[test]
+    fn zscore() {
+        let mut ys = vec![0.; 20];
+        ys[4] = 10.;
+        ys[9] = -3.;
+        let rows = |n| -> Vec<_> {
+            Outliers::ZScore(n)
+                .find(&data(&ys))
+                .iter()
+                .map(|o| o.row)
+                .collect()
+        };
+        assert_eq!(rows(3.), [4]);
+        assert_eq!(rows(1.), [4, 9]);
+        assert!(Outliers::ZScore(1.).find(&data(&[2., 2., 2.])).is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+use crate::graphics::Protocol;
+use std::str::FromStr;
+
+/// What to write the plot out as.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Output {
+    /// Text, for the terminal.
+    #[default]
+    Text,
+    /// A self-contained HTML page with an interactive chart (see [`crate::html`]).
+    Html,
+    /// A PNG image (see [`crate::raster`]).
+    Png,
+    /// An image shown right in the terminal, if it supports the protocol, and text otherwise.
+    Inline(Protocol),
+}
+
+impl Output {
+    /// Whether this is an image, and so `-d` is in pixels rather than cells.
+    pub(crate) fn is_image(self) -> bool {
+        self == Output::Png
+    }
+}
+
+impl FromStr for Output {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Output::Text),
+            "html" => Ok(Output::Html),
+            "png" => Ok(Output::Png),
+            "sixel" => Ok(Output::Inline(Protocol::Sixel)),
+            "kitty" => Ok(Output::Inline(Protocol::Kitty)),
+            _ => eyre::bail!("unknown output '{s}'; expected text, html, png, sixel, or kitty"),
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use eyre::Context;
+use std::fmt;
+use std::io::BufRead;
+
+/// One row of input values, in the order they appeared.
+///
+/// Values that are missing (or, outside of strict mode, invalid) are NaN.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Row {
+    pub(crate) values: Vec<f64>,
+    /// The unit of each value, if units are being parsed at all.
+    ///
+    /// The values have already been scaled to this unit (so `12ms` is 0.012 with unit `s`).
+    pub(crate) units: Vec<Option<String>>,
+}
+
+/// What kind of input to expect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Format {
+    /// Rows of whitespace-separated numbers.
+    #[default]
+    Text,
+    /// An HdrHistogram interval log (see [`crate::hlog::Log`]).
+    Hlog,
+}
+
+/// How to interpret the numbers in the input.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ParseOptions {
+    /// Whether invalid values are an error rather than treated as missing.
+    pub(crate) strict: bool,
+    /// Whether numbers may have thousands separators (`1,234.5`, or `1.234,5` with
+    /// `decimal_comma`).
+    pub(crate) thousands: bool,
+    /// Whether `,` rather than `.` separates the integer and fractional parts of a number.
+    pub(crate) decimal_comma: bool,
+    /// Whether numbers may be followed by a unit (`12ms`, `3.4k`, `1.2GiB`, `45%`).
+    pub(crate) units: bool,
+}
+
+impl ParseOptions {
+    fn is_number_char(&self, c: char) -> bool {
+        matches!(c, '0'..='9' | '-' | '+' | '.' | 'E' | 'e')
+            || (c == ',' && (self.thousands || self.decimal_comma))
+    }
+
+    fn is_unit_char(&self, c: char) -> bool {
+        self.units && (c.is_alphabetic() || c == '%')
+    }
+}
+
+/// The unit all the values along an axis were given in.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) enum Unit {
+    /// None of the values had a unit.
+    #[default]
+    None,
+    /// All the values that had a unit had this one.
+    Is(String),
+    /// The values had different units.
+    Mixed,
+}
+
+impl Unit {
+    /// Takes into account that a value had the given unit.
+    pub(crate) fn observe(&mut self, unit: &str) {
+        match self {
+            Unit::None => *self = Unit::Is(unit.to_string()),
+            Unit::Is(u) if u != unit => *self = Unit::Mixed,
+            Unit::Is(_) | Unit::Mixed => {}
+        }
+    }
+
+    /// Returns the unit of the rate of change of a value in `self` over a value in `over`.
+    pub(crate) fn per(&self, over: &Unit) -> Unit {
+        match (self, over) {
+            (Unit::Is(u), Unit::Is(v)) => Unit::Is(format!("{u}/{v}")),
+            // without knowing the unit of both, we can't know the unit of the rate
+            _ => Unit::None,
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    /// Writes the unit as a suffix (including the leading space) for an axis range.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unit::None => Ok(()),
+            Unit::Is(unit) => write!(f, " {unit}"),
+            Unit::Mixed => write!(f, " (mixed units)"),
+        }
+    }
+}
+
+/// Reads one [`Row`] per line of whitespace- (or otherwise) separated numbers.
+///
+/// Anything that yields rows as an `Iterator<Item = eyre::Result<Row>>` can be plotted, so other
+/// input formats can stand in for this one.
+#[derive(Debug)]
+pub(crate) struct Reader<R> {
+    input: R,
+    buf: String,
+    /// The (one-based) number of the last line read.
+    line: usize,
+    options: ParseOptions,
+    /// Whether the input may still grow, so a last line without a newline may not be whole yet.
+    following: bool,
+}
+
+impl<R> Reader<R> {
+    pub(crate) fn new(input: R, options: ParseOptions) -> Self {
+        Self {
+            input,
+            buf: String::new(),
+            line: 0,
+            options,
+            following: false,
+        }
+    }
+
+    /// Makes the reader only yield whole lines, so it can keep reading input that is still being
+    /// written to.
+    ///
+    /// Once it runs out of (whole) lines, it returns `None` until more are written. A line that
+    /// was only partially written is held on to until the rest of it comes in.
+    pub(crate) fn following(mut self) -> Self {
+        self.following = true;
+        self
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = eyre::Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // a partial line (when following) is kept in buf to be completed
+        if !self.following || self.buf.ends_with('\n') {
+            self.buf.clear();
+        }
+        let line = self.line + 1;
+        match self.input.read_line(&mut self.buf) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(Err(e).with_context(|| format!("read line {line}"))),
+        }
+        if self.following && !self.buf.ends_with('\n') {
+            return None;
+        }
+        self.line = line;
+
+        if self.options.strict {
+            // anything but whitespace has to be a number, rather than separate two of them
+            let invalid = self
+                .buf
+                .split_whitespace()
+                .find(|&word| !is_number(word, self.options));
+            if let Some(word) = invalid {
+                return Some(Err(eyre::eyre!(
+                    "line {line}: '{word}' is not a finite number"
+                )));
+            }
+        }
+
+        let mut row = Row::default();
+        for token in tokens(&self.buf, self.options) {
+            let (v, unit) = match parse_value(token, self.options) {
+                Some((v, unit)) if v.is_finite() => (v, unit),
+                _ => {
+                    // invalid (and infinite) values are treated as missing
+                    (f64::NAN, None)
+                }
+            };
+            row.values.push(v);
+            if self.options.units {
+                row.units.push(unit);
+            }
+        }
+        Some(Ok(row))
+    }
+}
+
+/// Splits a line of input into the runs of characters that can make up a number (and, if
+/// enabled, the unit that directly follows it).
+///
+/// Every other character separates two values, so two separators in a row means there's a
+/// missing value in between.
+fn tokens(line: &str, options: ParseOptions) -> impl Iterator<Item = &str> {
+    let mut line = line.trim_end();
+    std::iter::from_fn(move || {
+        if line.is_empty() {
+            return None;
+        }
+        let mut end = line
+            .find(|c| !options.is_number_char(c))
+            .unwrap_or(line.len());
+        if end > 0 {
+            end += line[end..]
+                .find(|c| !options.is_unit_char(c))
+                .unwrap_or(line.len() - end);
+        }
+        let next_num = &line[..end];
+        // skip the separator too
+        line = line[end..]
+            .char_indices()
+            .nth(1)
+            .map_or("", |(i, _)| &line[end + i..]);
+        Some(next_num)
+    })
+}
+
+/// Whether all of `word` is a single finite number (with its unit, if units are enabled), as
+/// strict mode requires of everything between whitespace.
+fn is_number(word: &str, options: ParseOptions) -> bool {
+    let mut tokens = tokens(word, options);
+    tokens.next() == Some(word)
+        && tokens.next().is_none()
+        && parse_value(word, options).is_some_and(|(v, _)| v.is_finite())
+}
+
+/// Parses a single token into a number and the unit it was scaled to (if any).
+fn parse_value(token: &str, options: ParseOptions) -> Option<(f64, Option<String>)> {
+    let split = token
+        .find(|c| !options.is_number_char(c))
+        .unwrap_or(token.len());
+    let (number, unit) = token.split_at(split);
+
+    let v = if options.thousands || options.decimal_comma {
+        let (separator, decimal) = if options.decimal_comma {
+            ('.', ',')
+        } else {
+            (',', '.')
+        };
+        let (integer, rest) = number
+            .find([decimal, 'e', 'E'])
+            .map_or((number, ""), |i| number.split_at(i));
+        let mut normalized = String::with_capacity(number.len());
+        if options.thousands {
+            let digits = integer.trim_start_matches(['-', '+']);
+            normalized.push_str(&integer[..integer.len() - digits.len()]);
+            for (i, group) in digits.split(separator).enumerate() {
+                // the leading group can be shorter, but the others must be exactly three digits
+                let valid = if i == 0 {
+                    (1..=3).contains(&group.len()) || !digits.contains(separator)
+                } else {
+                    group.len() == 3
+                };
+                if !valid {
+                    return None;
+                }
+                normalized.push_str(group);
+            }
+        } else {
+            if integer.contains(separator) {
+                return None;
+            }
+            normalized.push_str(integer);
+        }
+        if rest.contains(separator) {
+            return None;
+        }
+        normalized.push_str(&rest.replacen(decimal, ".", 1));
+        normalized.parse::<f64>().ok()?
+    } else {
+        number.parse::<f64>().ok()?
+    };
+
+    if unit.is_empty() {
+        return Some((v, None));
+    }
+    let (scale, unit) = parse_unit(unit)?;
+    Some((v * scale, (!unit.is_empty()).then(|| unit.to_string())))
+}
+
+/// Splits a unit into the factor its prefix (if any) scales by and the base unit.
+///
+/// Times are all converted to seconds. A lone prefix (as in `3.4k`) yields an empty base unit.
+fn parse_unit(unit: &str) -> Option<(f64, &str)> {
+    const TIME: &[(&str, f64)] = &[
+        ("ns", 1e-9),
+        ("us", 1e-6),
+        ("µs", 1e-6),
+        ("ms", 1e-3),
+        ("s", 1.),
+        ("min", 60.),
+        ("h", 3600.),
+        ("d", 86400.),
+    ];
+    const IEC: &[(&str, f64)] = &[
+        ("Ki", (1u64 << 10) as f64),
+        ("Mi", (1u64 << 20) as f64),
+        ("Gi", (1u64 << 30) as f64),
+        ("Ti", (1u64 << 40) as f64),
+        ("Pi", (1u64 << 50) as f64),
+    ];
+    const SI: &[(&str, f64)] = &[
+        ("n", 1e-9),
+        ("u", 1e-6),
+        ("µ", 1e-6),
+        ("m", 1e-3),
+        ("k", 1e3),
+        ("K", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+        ("T", 1e12),
+        ("P", 1e15),
+    ];
+
+    if unit == "%" {
+        return Some((1., unit));
+    }
+    if !unit.chars().all(char::is_alphabetic) {
+        return None;
+    }
+    if let Some(&(_, scale)) = TIME.iter().find(|(u, _)| *u == unit) {
+        return Some((scale, "s"));
+    }
+    for &(prefix, scale) in IEC.iter().chain(SI) {
+        if let Some(base) = unit.strip_prefix(prefix) {
+            return Some((scale, base));
+        }
+    }
+    // some unit we don't know any prefixes for
+    Some((1., unit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(input: &str, options: ParseOptions) -> eyre::Result<Vec<Vec<String>>> {
+        // NaN != NaN, so compare as strings
+        Reader::new(input.as_bytes(), options)
+            .map(|row| {
+                let row = row?;
+                let units = row.units.iter().map(Some).chain(std::iter::repeat(None));
+                Ok(row
+                    .values
+                    .iter()
+                    .zip(units)
+                    .map(|(v, unit)| match unit.and_then(Option::as_ref) {
+                        Some(unit) => format!("{v}{unit}"),
+                        None => v.to_string(),
+                    })
+                    .collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lenient() {
+        let rows = read("1 2\n3 - 4\n\n1e999 -2.5e1\n", ParseOptions::default()).unwrap();
+        assert_eq!(
+            rows,
+            [
+                vec!["1", "2"],
+                vec!["3", "NaN", "4"],
+                vec![],
+                vec!["NaN", "-25"]
+            ]
+        );
+    }
+
+    #[test]
+    fn strict() {
+        let strict = ParseOptions {
+            strict: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(read("1 2\n3 4\n", strict).unwrap().len(), 2);
+        // text that the lenient parser would take as separators is reported whole
+        for (input, line, word) in [
+            ("1 2\n3 1.2.3 4\n", 2, "1.2.3"),
+            ("1\nN/A\n3\n", 2, "N/A"),
+            ("1\n12abc\n", 2, "12abc"),
+            ("hello 1\n", 1, "hello"),
+            ("1 1e5\n2 1e\n", 2, "1e"),
+            ("1,5\n", 1, "1,5"),
+        ] {
+            let e = read(input, strict).unwrap_err();
+            assert_eq!(
+                e.to_string(),
+                format!("line {line}: '{word}' is not a finite number"),
+                "{input:?}"
+            );
+            assert!(read(input, ParseOptions::default()).is_ok());
+        }
+        let units = ParseOptions {
+            units: true,
+            ..strict
+        };
+        assert!(read("12ms 3.4k\n", units).is_ok());
+        assert!(read("12ms/s\n", units).is_err());
+        // runs of separators (as in column-aligned input) are missing values, just as they are
+        // outside of strict mode
+        let rows = read("1  2\n 3\t\t4\n", strict).unwrap();
+        assert_eq!(rows, [vec!["1", "NaN", "2"], vec!["NaN", "3", "NaN", "4"]]);
+        assert_eq!(
+            read("1  2\n 3\t\t4\n", ParseOptions::default()).unwrap(),
+            rows
+        );
+    }
+
+    #[test]
+    fn following_waits_for_whole_lines() {
+        let mut reader = Reader::new(
+            std::io::Cursor::new(b"1 2\n3".to_vec()),
+            ParseOptions::default(),
+        )
+        .following();
+        let mut next = || reader.next().transpose().unwrap().map(|row| row.values);
+        assert_eq!(next(), Some(vec![1., 2.]));
+        assert_eq!(next(), None);
+        reader.input.get_mut().extend(b"4 5\n");
+        assert_eq!(reader.next().unwrap().unwrap().values, [34., 5.]);
+        assert_eq!(reader.line, 2);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn thousands() {
+        let options = ParseOptions {
+            thousands: true,
+            ..ParseOptions::default()
+        };
+        let rows = read("1,234.5 -12,345,678 1234 12,34\n", options).unwrap();
+        assert_eq!(rows, [vec!["1234.5", "-12345678", "1234", "NaN"]]);
+    }
+
+    #[test]
+    fn decimal_comma() {
+        let options = ParseOptions {
+            decimal_comma: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            read("1,5 2 1.5\n", options).unwrap(),
+            [vec!["1.5", "2", "NaN"]]
+        );
+        let options = ParseOptions {
+            thousands: true,
+            ..options
+        };
+        assert_eq!(read("1.234,5\n", options).unwrap(), [vec!["1234.5"]]);
+    }
+
+    #[test]
+    fn units() {
+        let options = ParseOptions {
+            units: true,
+            ..ParseOptions::default()
+        };
+        let rows = read("12ms 3.4k 1GiB 45% 2min 3kHz 7\n", options).unwrap();
+        assert_eq!(
+            rows,
+            [vec![
+                "0.012s",
+                "3400",
+                "1073741824B",
+                "45%",
+                "120s",
+                "3000Hz",
+                "7"
+            ]]
+        );
+        // without --units, the suffix is just a separator
+        let rows = read("12ms 3\n", ParseOptions::default()).unwrap();
+        assert_eq!(rows, [vec!["12", "NaN", "NaN", "3"]]);
+    }
+
+    #[test]
+    fn unit_of_axis() {
+        let mut unit = Unit::None;
+        assert_eq!(unit.to_string(), "");
+        unit.observe("s");
+        unit.observe("s");
+        assert_eq!(unit.to_string(), " s");
+        assert_eq!(unit.per(&Unit::Is("B".into())).to_string(), " s/B");
+        unit.observe("B");
+        assert_eq!(unit, Unit::Mixed);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::raster::Raster;
+use std::io::Write;
+
+/// How far back (in bytes) a repeated run can refer to in deflate.
+const WINDOW: usize = 32 * 1024;
+
+/// How many earlier positions with the same next three bytes to try when looking for a run.
+const CHAIN: usize = 64;
+
+/// The shortest and longest runs deflate can refer back to.
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+/// The shortest length of each length code (from 257), and how many extra bits follow it.
+const LENGTHS: [(u16, u8); 29] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 1),
+    (13, 1),
+    (15, 1),
+    (17, 1),
+    (19, 2),
+    (23, 2),
+    (27, 2),
+    (31, 2),
+    (35, 3),
+    (43, 3),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 4),
+    (115, 4),
+    (131, 5),
+    (163, 5),
+    (195, 5),
+    (227, 5),
+    (258, 0),
+];
+
+/// The shortest distance of each distance code, and how many extra bits follow it.
+const DISTANCES: [(u16, u8); 30] = [
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 1),
+    (7, 1),
+    (9, 2),
+    (13, 2),
+    (17, 3),
+    (25, 3),
+    (33, 4),
+    (49, 4),
+    (65, 5),
+    (97, 5),
+    (129, 6),
+    (193, 6),
+    (257, 7),
+    (385, 7),
+    (513, 8),
+    (769, 8),
+    (1025, 9),
+    (1537, 9),
+    (2049, 10),
+    (3073, 10),
+    (4097, 11),
+    (6145, 11),
+    (8193, 12),
+    (12289, 12),
+    (16385, 13),
+    (24577, 13),
+];
+
+/// Writes bits into bytes, least significant bit first, as deflate wants them.
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    current: u32,
+    filled: u8,
+}
+
+impl Bits {
+    /// Writes the lowest `n` bits of `value`, lowest first.
+    fn write(&mut self, value: u32, n: u8) {
+        for i in 0..n {
+            self.current |= ((value >> i) & 1) << self.filled;
+            self.filled += 1;
+            if self.filled == 8 {
+                self.bytes.push(self.current as u8);
+                self.current = 0;
+                self.filled = 0;
+            }
+        }
+    }
+
+    /// Writes the `n`-bit Huffman `code`, which (unlike everything else) goes highest bit first.
+    fn code(&mut self, code: u32, n: u8) {
+        let reversed = code.reverse_bits() >> (32 - n);
+        self.write(reversed, n);
+    }
+
+    /// Writes the literal/length symbol `symbol` with the fixed Huffman code for it.
+    fn symbol(&mut self, symbol: u16) {
+        let symbol = u32::from(symbol);
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.filled > 0 {
+            self.bytes.push(self.current as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Hashes the three bytes of `data` starting at `i`, to find earlier runs that start the same.
+fn hash(data: &[u8], i: usize) -> usize {
+    let v = u32::from(data[i]) << 16 | u32::from(data[i + 1]) << 8 | u32::from(data[i + 2]);
+    (v.wrapping_mul(2654435761) >> 17) as usize
+}
+
+/// Earlier positions in the data being compressed, by the three bytes that start there.
+struct Seen {
+    /// For each hash, the last position it was seen at, plus one (so zero is none).
+    head: Vec<usize>,
+    /// For each position, the position before it with the same hash, plus one.
+    previous: Vec<usize>,
+}
+
+impl Seen {
+    fn remember(&mut self, data: &[u8], i: usize) {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(data, i);
+            self.previous[i] = self.head[h];
+            self.head[h] = i + 1;
+        }
+    }
+}
+
+/// Compresses `data` into a single deflate block with the fixed Huffman codes, replacing runs of
+/// bytes seen before with references back to them.
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut seen = Seen {
+        head: vec![0; 1 << 15],
+        previous: vec![0; data.len()],
+    };
+
+    let mut bits = Bits::default();
+    // final block, with fixed Huffman codes
+    bits.write(1, 1);
+    bits.write(1, 2);
+    let mut i = 0;
+    while i < data.len() {
+        let (mut best_len, mut best_distance) = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = seen.head[hash(data, i)];
+            for _ in 0..CHAIN {
+                let Some(at) = candidate.checked_sub(1) else {
+                    break;
+                };
+                if i - at > WINDOW {
+                    break;
+                }
+                let len = data[at..]
+                    .iter()
+                    .zip(&data[i..])
+                    .take(MAX_MATCH)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    (best_len, best_distance) = (len, i - at);
+                }
+                candidate = seen.previous[at];
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            let code = LENGTHS
+                .iter()
+                .rposition(|&(base, _)| usize::from(base) <= best_len)
+                .expect("every length is at least 3");
+            let (base, extra) = LENGTHS[code];
+            bits.symbol(257 + code as u16);
+            bits.write((best_len - usize::from(base)) as u32, extra);
+            let code = DISTANCES
+                .iter()
+                .rposition(|&(base, _)| usize::from(base) <= best_distance)
+                .expect("every distance is at least 1");
+            let (base, extra) = DISTANCES[code];
+            bits.code(code as u32, 5);
+            bits.write((best_distance - usize::from(base)) as u32, extra);
+            for j in i..i + best_len {
+                seen.remember(data, j);
+            }
+            i += best_len;
+        } else {
+            bits.symbol(u16::from(data[i]));
+            seen.remember(data, i);
+            i += 1;
+        }
+    }
+    bits.symbol(256);
+    bits.finish()
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Writes one PNG chunk of the given type.
+fn chunk(kind: &[u8; 4], data: &[u8], mut out: impl Write) -> std::io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    let mut checked = kind.to_vec();
+    checked.extend_from_slice(data);
+    out.write_all(&checked)?;
+    out.write_all(&crc32(&checked).to_be_bytes())
+}
+
+/// Writes `raster` as an (8-bit RGB) PNG image.
+pub(crate) fn write(raster: &Raster, mut out: impl Write) -> eyre::Result<()> {
+    let (width, height) = raster.size();
+    out.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, and the only compression, filtering, and (no) interlacing there is
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    chunk(b"IHDR", &header, &mut out)?;
+
+    // every row starts with its filter type, which is always none; the rows of a plot are
+    // mostly runs of background anyway, which deflate takes care of
+    let mut rows = Vec::with_capacity(height * (1 + 3 * width));
+    for row in raster.rows() {
+        rows.push(0);
+        rows.extend(row.iter().flatten());
+    }
+    let mut compressed = vec![0x78, 0x01];
+    compressed.extend(deflate(&rows));
+    compressed.extend_from_slice(&adler32(&rows).to_be_bytes());
+    chunk(b"IDAT", &compressed, &mut out)?;
+
+    chunk(b"IEND", &[], &mut out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn deflate_refers_back_to_runs() {
+        // a literal 'a', then one reference back to it for the other 99
+        let compressed = deflate(&[b'a'; 100]);
+        assert_eq!(compressed.len(), 5, "{compressed:?}");
+        // the header of a final block with fixed codes
+        assert_eq!(compressed[0] & 0b111, 0b011);
+    }
+}
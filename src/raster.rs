@@ -0,0 +1,422 @@
+use crate::annotate::Annotation;
+use crate::data::COLORS;
+use crate::font::{self, GLYPH};
+use crate::{Data, Frame};
+
+/// A color, as red, green, and blue.
+pub(crate) type Rgb = [u8; 3];
+
+const BACKGROUND: Rgb = [0xff, 0xff, 0xff];
+const INK: Rgb = [0x33, 0x33, 0x33];
+const AXIS: Rgb = [0x88, 0x88, 0x88];
+const GRID: Rgb = [0xdd, 0xdd, 0xdd];
+const BAND: Rgb = [0xe4, 0xe4, 0xf2];
+const REFERENCE: Rgb = [0xaa, 0x55, 0x55];
+
+/// `color` mixed with the background, for points drawn faintly underneath others.
+fn faint(color: Rgb) -> Rgb {
+    color.map(|c| ((u16::from(c) + 2 * 0xff) / 3) as u8)
+}
+
+/// An image to draw a plot onto, for graphical output.
+///
+/// Pixel `(x, y)` covers `x..x + 1` and `y..y + 1`, with `y` going down from the top.
+pub(crate) struct Raster {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Raster {
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![BACKGROUND; width * height],
+        }
+    }
+
+    pub(crate) fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub(crate) fn rows(&self) -> impl Iterator<Item = &[Rgb]> {
+        self.pixels.chunks_exact(self.width)
+    }
+
+    /// Mixes `color` into the pixel at `(x, y)` by `coverage` (from 0 to 1).
+    ///
+    /// Pixels outside of the image are left out, so shapes are cut off at its edges.
+    fn blend(&mut self, x: isize, y: isize, color: Rgb, coverage: f64) {
+        if coverage <= 0. || x < 0 || y < 0 || x as usize >= self.width {
+            return;
+        }
+        let Some(pixel) = self.pixels.get_mut(y as usize * self.width + x as usize) else {
+            return;
+        };
+        let coverage = coverage.min(1.);
+        for (p, c) in pixel.iter_mut().zip(color) {
+            *p = (f64::from(*p) * (1. - coverage) + f64::from(c) * coverage).round() as u8;
+        }
+    }
+
+    /// Blends `color` into every pixel of the box between the two corners by how much of it
+    /// `coverage` says is covered, given the center of the pixel.
+    fn cover(
+        &mut self,
+        (x0, y0): (f64, f64),
+        (x1, y1): (f64, f64),
+        color: Rgb,
+        coverage: impl Fn(f64, f64) -> f64,
+    ) {
+        for y in y0.floor() as isize..=y1.ceil() as isize {
+            for x in x0.floor() as isize..=x1.ceil() as isize {
+                let c = coverage(x as f64 + 0.5, y as f64 + 0.5);
+                self.blend(x, y, color, c);
+            }
+        }
+    }
+
+    /// Draws an anti-aliased line `thickness` pixels wide between two points.
+    fn line(&mut self, from: (f64, f64), to: (f64, f64), thickness: f64, color: Rgb) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length2 = dx * dx + dy * dy;
+        let reach = thickness / 2. + 1.;
+        let corner = (from.0.min(to.0) - reach, from.1.min(to.1) - reach);
+        let other = (from.0.max(to.0) + reach, from.1.max(to.1) + reach);
+        self.cover(corner, other, color, |x, y| {
+            // the distance to the closest point on the segment
+            let t = if length2 == 0. {
+                0.
+            } else {
+                (((x - from.0) * dx + (y - from.1) * dy) / length2).clamp(0., 1.)
+            };
+            let (px, py) = (from.0 + t * dx, from.1 + t * dy);
+            let distance = ((x - px).powi(2) + (y - py).powi(2)).sqrt();
+            (thickness / 2. + 0.5 - distance).clamp(0., 1.)
+        });
+    }
+
+    /// Draws an anti-aliased, filled circle.
+    fn disc(&mut self, (cx, cy): (f64, f64), radius: f64, color: Rgb) {
+        let reach = radius + 1.;
+        let (corner, other) = ((cx - reach, cy - reach), (cx + reach, cy + reach));
+        self.cover(corner, other, color, |x, y| {
+            let distance = ((x - cx).powi(2) + (y - cy).powi(2)).sqrt();
+            (radius + 0.5 - distance).clamp(0., 1.)
+        });
+    }
+
+    /// Fills the rectangle with its top-left corner at `(x, y)`.
+    fn rect(&mut self, (x, y): (usize, usize), (width, height): (usize, usize), color: Rgb) {
+        for y in y..y + height {
+            for x in x..x + width {
+                self.blend(x as isize, y as isize, color, 1.);
+            }
+        }
+    }
+
+    /// Writes `text` in the built-in font, with every pixel of it `scale` pixels wide, starting
+    /// with its top-left corner at `(x, y)`.
+    fn text(&mut self, (x, y): (usize, usize), text: &str, scale: usize, color: Rgb) {
+        for (i, c) in text.chars().enumerate() {
+            let left = x + i * (GLYPH.0 + 1) * scale;
+            for (row, bits) in font::glyph(c).iter().enumerate() {
+                for column in 0..GLYPH.0 {
+                    if bits & (1 << (GLYPH.0 - 1 - column)) != 0 {
+                        let at = (left + column * scale, y + row * scale);
+                        self.rect(at, (scale, scale), color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// How wide `text` is when written with [`Raster::text`].
+fn text_width(text: &str, scale: usize) -> usize {
+    text.chars().count() * (GLYPH.0 + 1) * scale
+}
+
+/// Draws the points of `data` within the bounds of `frame` as an image of `width` by `height`
+/// pixels, along with the `raw` points underneath them (faintly) and the `annotations`.
+///
+/// It's laid out like the text plot, with the range of each axis and a legend on top, and the
+/// axes through zero where they can be. Each axis is also labelled, and points go where the frame
+/// puts them, only at a finer resolution than one cell.
+pub(crate) fn plot(
+    data: &Data,
+    raw: Option<&Data>,
+    annotations: &[Annotation],
+    frame: &Frame,
+    percentile_x: bool,
+    draw_axes: bool,
+    (width, height): (usize, usize),
+) -> Raster {
+    let mut raster = Raster::new(width, height);
+    // everything grows with the image, so the text stays legible
+    let scale = (width / 400).min(height / 225).max(1);
+    let line = (GLYPH.1 + 4) * scale;
+    let pad = 4 * scale;
+
+    let (x_scale, y_scale) = (frame.x_scale(), frame.y_scale());
+    let ((min_x, max_x), (min_y, max_y)) = (frame.x_axis(), frame.y_axis());
+    let x_ticks = if percentile_x {
+        crate::percentile_ticks(frame)
+    } else {
+        x_scale.chart_ticks(min_x, max_x)
+    };
+    let y_ticks = y_scale.chart_ticks(min_y, max_y);
+
+    // room for the Y tick labels on the left, half of the last X tick label on the right, the
+    // ranges and legend on top, and the X tick labels at the bottom.
+    let widest = y_ticks.iter().map(|(_, label)| text_width(label, scale));
+    let left = 2 * pad + widest.max().unwrap_or(0);
+    let last = x_ticks.last().map(|(_, label)| text_width(label, scale));
+    let right = pad + last.unwrap_or(0) / 2;
+    let top = 2 * pad + 2 * line;
+    let bottom = pad + line;
+    let plot_width = width.saturating_sub(left + right).max(1) as f64;
+    let plot_height = height.saturating_sub(top + bottom).max(1) as f64;
+    let to_pixel = |(fx, fy): (f64, f64)| {
+        (
+            left as f64 + fx * plot_width,
+            top as f64 + (1. - fy) * plot_height,
+        )
+    };
+    let thickness = scale as f64;
+    // where a value (in the units of the data) is along each axis, if it is within the frame
+    let within = |fraction: f64| (0. ..=1.).contains(&fraction).then_some(fraction);
+    let x_at = |x| within(frame.x_fraction(x_scale.apply(x)?));
+    let y_at = |y| within(frame.y_fraction(y_scale.apply(y)?));
+
+    // like in the text plot, bands go underneath everything else
+    for annotation in annotations {
+        if let Annotation::Band { from, to, .. } = *annotation {
+            if let (Some(from), Some(to)) = (y_at(from), y_at(to)) {
+                let ((x0, y0), (x1, y1)) = (to_pixel((0., to)), to_pixel((1., from)));
+                raster.cover((x0, y0), (x1, y1), BAND, |x, y| {
+                    let inside = |v: f64, low: f64, high: f64| (v - low).min(high - v) + 0.5;
+                    inside(x, x0, x1).min(inside(y, y0, y1)).clamp(0., 1.)
+                });
+            }
+        }
+    }
+
+    for (x, label) in &x_ticks {
+        let fx = frame.x_fraction(*x);
+        raster.line(to_pixel((fx, 0.)), to_pixel((fx, 1.)), thickness, GRID);
+        let (px, py) = to_pixel((fx, 0.));
+        let at = (px as usize).saturating_sub(text_width(label, scale) / 2);
+        raster.text((at, py as usize + pad), label, scale, INK);
+    }
+    for (y, label) in &y_ticks {
+        let fy = frame.y_fraction(*y);
+        raster.line(to_pixel((0., fy)), to_pixel((1., fy)), thickness, GRID);
+        let (_, py) = to_pixel((0., fy));
+        let at = left - pad - text_width(label, scale);
+        let py = (py as usize).saturating_sub(GLYPH.1 * scale / 2);
+        raster.text((at, py), label, scale, INK);
+    }
+
+    if draw_axes {
+        // like in the text plot, the axes go through zero if it's in view, and along the edge
+        // closest to it if not.
+        let zero_or_edge = |min: f64, max: f64| {
+            if min <= 0. && max >= 0. {
+                0.
+            } else if min > 0. {
+                min
+            } else {
+                max
+            }
+        };
+        let fx = frame.x_fraction(zero_or_edge(min_x, max_x));
+        let fy = frame.y_fraction(zero_or_edge(min_y, max_y));
+        raster.line(to_pixel((0., fy)), to_pixel((1., fy)), thickness, AXIS);
+        raster.line(to_pixel((fx, 0.)), to_pixel((fx, 1.)), thickness, AXIS);
+    }
+
+    // reference lines go on top of the axes, but underneath the data
+    for annotation in annotations {
+        match *annotation {
+            Annotation::HLine { y, .. } => {
+                if let Some(fy) = y_at(y) {
+                    raster.line(to_pixel((0., fy)), to_pixel((1., fy)), thickness, REFERENCE);
+                }
+            }
+            Annotation::VLine { x, .. } => {
+                if let Some(fx) = x_at(x) {
+                    raster.line(to_pixel((fx, 0.)), to_pixel((fx, 1.)), thickness, REFERENCE);
+                }
+            }
+            Annotation::Band { .. } => {}
+        }
+    }
+
+    let radius = 1.5 * scale as f64;
+    let mut points = |data: &Data, color: &dyn Fn(usize) -> Rgb| {
+        for (column, ys) in data.ys.iter().enumerate() {
+            for (&x, &y) in data.xs.iter().zip(ys) {
+                if !x.is_finite() || !y.is_finite() {
+                    continue;
+                }
+                // points that can't be drawn on the scales were counted as dropped by the frame
+                let Some(at) = frame.point_to_fraction((x, y)) else {
+                    continue;
+                };
+                raster.disc(to_pixel(at), radius, color(column));
+            }
+        }
+    };
+    if let Some(raw) = raw {
+        points(raw, &|column| faint(COLORS[column % COLORS.len()]));
+    }
+    points(data, &|column| COLORS[column % COLORS.len()]);
+
+    // labels go on top of everything, so that they can always be read
+    let right = left as f64 + plot_width;
+    for annotation in annotations {
+        let (at, label) = match annotation {
+            // right-aligned just above the line, or the top edge of the band
+            Annotation::HLine {
+                y,
+                label: Some(label),
+            }
+            | Annotation::Band {
+                to: y,
+                label: Some(label),
+                ..
+            } => {
+                let Some(fy) = y_at(*y) else { continue };
+                let (_, py) = to_pixel((0., fy));
+                // a label wider than the plot starts at the left edge of the image, and is cut off
+                let px = (right as usize).saturating_sub(pad + text_width(label, scale));
+                (
+                    (px, (py as usize).saturating_sub(pad + GLYPH.1 * scale)),
+                    label,
+                )
+            }
+            // along the top, to the right of the line if it fits, and to its left if not
+            Annotation::VLine {
+                x,
+                label: Some(label),
+            } => {
+                let Some(fx) = x_at(*x) else { continue };
+                let (px, _) = to_pixel((fx, 1.));
+                let px = px as usize;
+                let px = if px + pad + text_width(label, scale) <= right as usize {
+                    px + pad
+                } else {
+                    px.saturating_sub(pad + text_width(label, scale))
+                };
+                ((px, top + pad), label)
+            }
+            _ => continue,
+        };
+        raster.text(at, label, scale, REFERENCE);
+    }
+
+    let ranges = crate::ranges(data, frame, percentile_x);
+    raster.text((pad, pad), ranges.trim(), scale, INK);
+    let mut at = pad;
+    for column in 0..data.ys.len() {
+        let color = COLORS[column % COLORS.len()];
+        let swatch = GLYPH.1 * scale;
+        raster.rect((at, pad + line), (swatch, swatch), color);
+        at += swatch + pad;
+        let label = data.label(column);
+        raster.text((at, pad + line), &label, scale, INK);
+        at += text_width(&label, scale) + 3 * pad;
+    }
+
+    raster
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_go_where_the_frame_puts_them() {
+        let data = Data {
+            xs: vec![0., 10.],
+            ys: vec![vec![0., 10.]],
+            ..Data::default()
+        };
+        let frame = Frame::new_over(80, 20, &data);
+        let raster = plot(&data, None, &[], &frame, false, true, (400, 300));
+        let rows: Vec<_> = raster.rows().collect();
+        // the top-right point is at the top-right corner of the plotting area, whereas the
+        // corner of the image is background
+        let (right, top) = (400 - 4 - text_width("10", 1) / 2, 2 * 4 + 2 * 11);
+        assert_eq!(rows[top][right - 1], COLORS[0]);
+        assert_eq!(rows[0][399], BACKGROUND);
+    }
+
+    #[test]
+    fn annotations_and_raw_points_are_drawn() {
+        let data = Data {
+            xs: vec![0., 10.],
+            ys: vec![vec![0., 10.]],
+            ..Data::default()
+        };
+        let raw = Data {
+            xs: vec![0.],
+            ys: vec![vec![5.]],
+            ..Data::default()
+        };
+        let annotations = [
+            Annotation::hline("2:SLO").unwrap(),
+            Annotation::band("6:8").unwrap(),
+        ];
+        let frame = Frame::new_over(80, 20, &data);
+        let has = |raster: &Raster, color: Rgb| raster.rows().flatten().any(|&p| p == color);
+
+        let plain = plot(&data, None, &[], &frame, false, false, (400, 300));
+        let raster = plot(
+            &data,
+            Some(&raw),
+            &annotations,
+            &frame,
+            false,
+            false,
+            (400, 300),
+        );
+        for color in [REFERENCE, BAND, faint(COLORS[0])] {
+            assert!(!has(&plain, color), "{color:?}");
+            assert!(has(&raster, color), "{color:?}");
+        }
+    }
+
+    #[test]
+    fn labels_wider_than_the_image_are_cut_off() {
+        let data = Data {
+            xs: vec![0., 10.],
+            ys: vec![vec![0., 10.]],
+            ..Data::default()
+        };
+        let label = "a label that is a lot wider than the image it is drawn on";
+        let annotations = [
+            Annotation::hline(&format!("5:{label}")).unwrap(),
+            Annotation::band(&format!("6:8:{label}")).unwrap(),
+            Annotation::vline(&format!("5:{label}")).unwrap(),
+        ];
+        let frame = Frame::new_over(80, 20, &data);
+        let raster = plot(&data, None, &annotations, &frame, false, true, (100, 60));
+        assert_eq!(raster.size(), (100, 60));
+        assert!(raster.rows().flatten().any(|&p| p == REFERENCE));
+    }
+
+    #[test]
+    fn lines_are_anti_aliased() {
+        let mut raster = Raster::new(10, 10);
+        raster.line((0., 5.), (10., 5.), 1., INK);
+        let rows: Vec<_> = raster.rows().collect();
+        // a line along the boundary between two rows of pixels covers half of each
+        assert_ne!(rows[4][5], BACKGROUND);
+        assert_ne!(rows[4][5], INK);
+        assert_eq!(rows[4][5], rows[5][5]);
+        assert_eq!(rows[2][5], BACKGROUND);
+    }
+}
@@ -0,0 +1,373 @@
+use crate::{Data, Scale};
+use std::collections::HashMap;
+
+/// How many buckets to keep along an axis for each cell of the canvas along that axis.
+///
+/// Since the final bounds of the plot aren't known while the input is being read, the buckets
+/// can end up covering a range several times wider than the data. This needs to be large enough
+/// that there are still at least two buckets per cell when that happens. It must also be even so
+/// that buckets can be merged pairwise.
+const BUCKETS_PER_CELL: usize = 8;
+
+/// A bounded-size summary of an arbitrarily long stream of points.
+///
+/// Both axes, as drawn on their scales, are split into a fixed number of equally wide buckets,
+/// and for each series and each (X, Y) bucket that any point fell into, only the points with the
+/// lowest X, highest X, lowest Y, and highest Y are kept, along with how many points fell into
+/// it. Values that can't be drawn on the scale of their axis (like 0 on a log axis) all share a
+/// bucket of their own along that axis, so that they still count towards the bounds of the other
+/// axis, and as dropped. When a point arrives that lies outside the buckets along an axis, the
+/// bucket width along that axis is doubled (merging neighbouring buckets) until it fits. Memory use
+/// is therefore proportional to the size of the canvas rather than to the length of the input.
+///
+/// Since there are at least two buckets per cell, a bucket straddles at most one cell edge
+/// along each axis, and the kept extremes then fall on either side of it. So, the plot is drawn
+/// the same as without reduction, except in the rare case where a bucket straddles a corner. The
+/// bounds of the plot are always the same. In [`Mode::Count`](crate::Mode::Count), the count of
+/// a bucket goes to the cell of one of its kept points (see [`Data::weights`]), so the counts are
+/// the same too, except that where a bucket straddles a cell edge, its count all ends up on one
+/// side of it.
+#[derive(Debug)]
+pub(crate) struct Reducer {
+    x: Axis,
+    y: Axis,
+    /// The scales of the X and Y axes as drawn.
+    scales: (Scale, Scale),
+    /// For each series, the extremes of each (X, Y) bucket.
+    series: Vec<HashMap<Bucket, Extremes>>,
+}
+
+/// The bucket along each axis, or `None` for a value that can't be drawn on the scale of its axis.
+type Bucket = (Option<usize>, Option<usize>);
+
+/// The points with the lowest X, highest X, lowest Y, and highest Y, out of `count` points.
+#[derive(Debug, Copy, Clone)]
+struct Extremes {
+    points: [(f64, f64); 4],
+    count: usize,
+}
+
+impl Extremes {
+    fn of(point: (f64, f64)) -> Self {
+        Self {
+            points: [point; 4],
+            count: 1,
+        }
+    }
+
+    fn merge(
+        &mut self,
+        Extremes {
+            points: other,
+            count,
+        }: Extremes,
+    ) {
+        self.count += count;
+        let [min_x, max_x, min_y, max_y] = &mut self.points;
+        if other[0].0 < min_x.0 {
+            *min_x = other[0];
+        }
+        if other[1].0 > max_x.0 {
+            *max_x = other[1];
+        }
+        if other[2].1 < min_y.1 {
+            *min_y = other[2];
+        }
+        if other[3].1 > max_y.1 {
+            *max_y = other[3];
+        }
+    }
+}
+
+fn merge_into(buckets: &mut HashMap<Bucket, Extremes>, at: Bucket, e: Extremes) {
+    buckets
+        .entry(at)
+        .and_modify(|existing| existing.merge(e))
+        .or_insert(e);
+}
+
+/// Equally wide buckets along one axis that double in width as needed to cover all values.
+#[derive(Debug)]
+struct Axis {
+    /// The value at the low edge of the first bucket, or NaN before the first value.
+    origin: f64,
+    width: f64,
+    buckets: usize,
+}
+
+impl Axis {
+    fn new(cells: usize) -> Self {
+        Self {
+            origin: f64::NAN,
+            width: 0.,
+            buckets: cells.max(1) * BUCKETS_PER_CELL,
+        }
+    }
+
+    /// Returns the bucket the (scaled) value `v` falls into, or which way the axis must grow for
+    /// it to fit.
+    fn bucket(&mut self, v: f64) -> Result<usize, Grow> {
+        if self.origin.is_nan() {
+            // we don't know the scale of the axis yet, so start out as fine-grained as we can and
+            // let `grow` coarsen the buckets as the data demands.
+            self.origin = v;
+            self.width = f64::EPSILON * v.abs().max(1.);
+        }
+        let offset = (v - self.origin) / self.width;
+        if offset < 0. {
+            Err(Grow::Down)
+        } else if offset >= self.buckets as f64 {
+            Err(Grow::Up)
+        } else {
+            Ok(offset as usize)
+        }
+    }
+
+    /// Doubles the width of every bucket, and returns the new index of the old bucket 0.
+    ///
+    /// Old bucket `i` ends up in new bucket `shift + i / 2`.
+    fn grow(&mut self, direction: Grow) -> usize {
+        let shift = match direction {
+            Grow::Up => 0,
+            Grow::Down => {
+                self.origin -= self.width * self.buckets as f64;
+                // the old buckets now make up the upper half of the new range
+                self.buckets / 2
+            }
+        };
+        self.width *= 2.;
+        shift
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Grow {
+    Down,
+    Up,
+}
+
+impl Reducer {
+    /// Makes a summary for a canvas of the given size, with the given scales along its axes.
+    pub(crate) fn new(canvas_width: usize, canvas_height: usize, scales: (Scale, Scale)) -> Self {
+        Self {
+            x: Axis::new(canvas_width),
+            y: Axis::new(canvas_height),
+            scales,
+            series: Vec::new(),
+        }
+    }
+
+    /// Moves all the points in `data` into the summary, leaving `data` empty (but with the same
+    /// number of series).
+    pub(crate) fn absorb(&mut self, data: &mut Data) {
+        for (row, x) in data.xs.drain(..).enumerate() {
+            if !x.is_finite() {
+                continue;
+            }
+            for (series, ys) in data.ys.iter().enumerate() {
+                let y = ys[row];
+                if y.is_finite() {
+                    self.push(series, (x, y));
+                }
+            }
+        }
+        for ys in &mut data.ys {
+            ys.clear();
+        }
+        data.lines.clear();
+    }
+
+    fn push(&mut self, series: usize, (x, y): (f64, f64)) {
+        if self.series.len() <= series {
+            self.series.resize_with(series + 1, HashMap::new);
+        }
+        let (scaled_x, scaled_y) = (self.scales.0.apply(x), self.scales.1.apply(y));
+        loop {
+            let i = scaled_x.map(|x| self.x.bucket(x)).transpose();
+            let j = scaled_y.map(|y| self.y.bucket(y)).transpose();
+            match (i, j) {
+                (Ok(i), Ok(j)) => {
+                    merge_into(&mut self.series[series], (i, j), Extremes::of((x, y)));
+                    return;
+                }
+                (Err(direction), _) => {
+                    let shift = self.x.grow(direction);
+                    self.rebucket(|(i, j)| (i.map(|i| shift + i / 2), j));
+                }
+                (_, Err(direction)) => {
+                    let shift = self.y.grow(direction);
+                    self.rebucket(|(i, j)| (i, j.map(|j| shift + j / 2)));
+                }
+            }
+        }
+    }
+
+    fn rebucket(&mut self, f: impl Fn(Bucket) -> Bucket) {
+        for buckets in &mut self.series {
+            for (at, extremes) in std::mem::take(buckets) {
+                merge_into(buckets, f(at), extremes);
+            }
+        }
+    }
+
+    /// Turns the summary back into a data set holding only the kept points.
+    pub(crate) fn into_data(self) -> Data {
+        let mut data = Data {
+            ys: vec![Vec::new(); self.series.len()],
+            ..Data::default()
+        };
+        let by_x_then_y =
+            |a: &(f64, f64), b: &(f64, f64)| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1));
+        for (column, buckets) in self.series.into_iter().enumerate() {
+            let mut points = Vec::new();
+            for Extremes {
+                points: mut extremes,
+                count,
+            } in buckets.into_values()
+            {
+                // the same point is often the extreme in more than one way
+                extremes.sort_by(by_x_then_y);
+                let mut kept = extremes.to_vec();
+                kept.dedup();
+                // the first kept point stands for all the points that weren't kept
+                let rest = kept.len() - 1;
+                points.extend(kept.into_iter().enumerate().map(|(i, (x, y))| {
+                    let weight = if i == 0 { count - rest } else { 1 };
+                    (x, y, weight)
+                }));
+            }
+            points.sort_by(|a, b| by_x_then_y(&(a.0, a.1), &(b.0, b.1)));
+            for (x, y, weight) in points {
+                data.xs.push(x);
+                data.weights.push(weight);
+                for (c, ys) in data.ys.iter_mut().enumerate() {
+                    ys.push(if c == column { y } else { f64::NAN });
+                }
+            }
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Canvas, Charset, Frame, Mode};
+
+    /// The plot of `data` drawn onto a canvas of the given size and scales.
+    fn draw(
+        data: &Data,
+        (width, height): (usize, usize),
+        (x_scale, y_scale): (Scale, Scale),
+    ) -> String {
+        let frame = Frame::new_scaled_over(width, height, data, x_scale, y_scale);
+        let mut canvas = Canvas::new(height, width, Mode::Dot, Charset::Ascii);
+        frame.draw_into(&mut canvas);
+        data.draw_into(&mut canvas, &frame);
+        format!(
+            "{:?}\n{canvas}",
+            (frame.x_bounds(), frame.y_bounds(), frame.dropped())
+        )
+    }
+
+    /// Reduces `data` for a canvas of the given size and scales.
+    fn reduce(data: &Data, (width, height): (usize, usize), scales: (Scale, Scale)) -> Data {
+        let mut reducer = Reducer::new(width, height, scales);
+        reducer.absorb(&mut data.clone());
+        reducer.into_data()
+    }
+
+    #[test]
+    fn buckets_double_to_fit() {
+        let mut axis = Axis::new(1);
+        assert_eq!(axis.bucket(0.), Ok(0));
+        let width = axis.width;
+        let mut grown = 0;
+        while let Err(direction) = axis.bucket(1.) {
+            assert_eq!(direction, Grow::Up);
+            assert_eq!(axis.grow(direction), 0);
+            grown += 1;
+        }
+        assert_eq!(axis.width, width * 2f64.powi(grown));
+        assert_eq!(axis.bucket(0.), Ok(0));
+        // it didn't fit at half the width, so it's in the upper half
+        assert!(axis.bucket(1.).unwrap() >= BUCKETS_PER_CELL / 2);
+
+        // growing down leaves the old buckets as the upper half of the new ones
+        assert_eq!(axis.bucket(-1.), Err(Grow::Down));
+        assert_eq!(axis.grow(Grow::Down), BUCKETS_PER_CELL / 2);
+        assert_eq!(axis.bucket(0.), Ok(BUCKETS_PER_CELL / 2));
+        assert!(axis.bucket(-1.).is_ok());
+    }
+
+    #[test]
+    fn keeps_extremes_and_counts() {
+        let data = Data {
+            xs: (0..1000).map(f64::from).collect(),
+            ys: vec![
+                (0..1000).map(|i| f64::from(i % 7)).collect(),
+                (0..1000).map(|i| -f64::from(i * i)).collect(),
+            ],
+            ..Data::default()
+        };
+        let reduced = reduce(&data, (4, 4), (Scale::Linear, Scale::Linear));
+        assert!(reduced.xs.len() < data.xs.len());
+        for (column, ys) in data.ys.iter().enumerate() {
+            let kept: Vec<_> = (0..reduced.xs.len())
+                .filter(|&row| reduced.ys[column][row].is_finite())
+                .collect();
+            let weights: usize = kept.iter().map(|&row| reduced.weight(row)).sum();
+            assert_eq!(weights, ys.len(), "column {column}");
+
+            let min_max = |vs: &mut dyn Iterator<Item = f64>| {
+                let vs: Vec<_> = vs.collect();
+                let min = vs.iter().copied().min_by(f64::total_cmp);
+                (min, vs.into_iter().max_by(f64::total_cmp))
+            };
+            let xs =
+                |rows: &[usize]| -> Vec<f64> { rows.iter().map(|&row| reduced.xs[row]).collect() };
+            assert_eq!(min_max(&mut xs(&kept).into_iter()), (Some(0.), Some(999.)));
+            assert_eq!(
+                min_max(&mut kept.iter().map(|&row| reduced.ys[column][row])),
+                min_max(&mut ys.iter().copied())
+            );
+        }
+    }
+
+    #[test]
+    fn memory_is_bounded_by_the_canvas() {
+        let (width, height) = (10, 5);
+        let mut reducer = Reducer::new(width, height, (Scale::Linear, Scale::Linear));
+        // a pseudo-random walk that keeps widening the range of both axes
+        let mut y = 0.;
+        for i in 0..200_000 {
+            y += f64::from((i * 7919) % 101) - 50.;
+            reducer.push(0, (f64::from(i), y));
+        }
+        let buckets = width * BUCKETS_PER_CELL * height * BUCKETS_PER_CELL;
+        assert!(reducer.series[0].len() <= buckets);
+        let reduced = reducer.into_data();
+        assert!(reduced.xs.len() <= 4 * buckets);
+        assert_eq!(reduced.weights.iter().sum::<usize>(), 200_000);
+    }
+
+    #[test]
+    fn draws_the_same_on_a_log_scale() {
+        // spread evenly over five decades, so most of them fall in the bottom few percent of a
+        // linear axis
+        let n = 200_000;
+        let ys: Vec<f64> = (0..n)
+            .map(|i| 10f64.powf(5. * f64::from((i * 7919) % n) / f64::from(n)))
+            .chain([0., -1.])
+            .collect();
+        let data = Data {
+            xs: (0..ys.len()).map(|i| i as f64).collect(),
+            ys: vec![ys],
+            ..Data::default()
+        };
+        let (size, scales) = ((60, 15), (Scale::Linear, Scale::Log(10.)));
+        let reduced = reduce(&data, size, scales);
+        assert!(reduced.xs.len() < data.xs.len() / 2);
+        assert_eq!(draw(&reduced, size, scales), draw(&data, size, scales));
+    }
+}
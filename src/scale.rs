@@ -0,0 +1,258 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// The smallest base a log scale can have.
+///
+/// There's a tick at each whole power of the base, and the closer the base is to 1, the more of
+/// them there are (a thousand between 1 and 10 with a base of 1.0023). Bases below 1 would also
+/// flip the axis.
+const MIN_LOG_BASE: f64 = 1.5;
+
+/// How the values along an axis are mapped onto it.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub(crate) enum Scale {
+    #[default]
+    Linear,
+    /// The logarithm of each value in the given base.
+    ///
+    /// Values that aren't positive have no logarithm, and so can't be drawn.
+    Log(f64),
+    /// `sign(v) * log10(1 + |v|)`, which is roughly linear close to zero and logarithmic further
+    /// out, so it works for values of either sign (and zero).
+    SymLog,
+}
+
+impl FromStr for Scale {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Scale::Linear),
+            "log" => Ok(Scale::Log(10.)),
+            "symlog" => Ok(Scale::SymLog),
+            _ => {
+                let Some(base) = s.strip_prefix("log:") else {
+                    eyre::bail!("unknown scale '{s}'; expected linear, log, log:BASE, or symlog");
+                };
+                let base: f64 = base
+                    .parse()
+                    .map_err(|e| eyre::eyre!("invalid log base '{base}': {e}"))?;
+                eyre::ensure!(
+                    base >= MIN_LOG_BASE && base.is_finite(),
+                    "log base must be at least {MIN_LOG_BASE} (so there aren't too many powers of \
+                     it to mark along the axis), got {base}"
+                );
+                Ok(Scale::Log(base))
+            }
+        }
+    }
+}
+
+/// Names the scale in front of an axis, as in `log x: [...]`; nothing for a linear axis.
+impl fmt::Display for Scale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scale::Linear => Ok(()),
+            Scale::Log(base) if *base == 10. => write!(f, "log "),
+            Scale::Log(base) => write!(f, "log{base} "),
+            Scale::SymLog => write!(f, "symlog "),
+        }
+    }
+}
+
+impl Scale {
+    pub(crate) fn is_linear(self) -> bool {
+        self == Scale::Linear
+    }
+
+    /// Names the scale the way `--x-scale` takes it.
+    pub(crate) fn name(self) -> String {
+        match self {
+            Scale::Linear => "linear".to_string(),
+            Scale::Log(10.) => "log".to_string(),
+            Scale::Log(base) => format!("log:{base}"),
+            Scale::SymLog => "symlog".to_string(),
+        }
+    }
+
+    /// Maps `v` to where it goes along the axis, or `None` if it can't be drawn on this scale.
+    pub(crate) fn apply(self, v: f64) -> Option<f64> {
+        match self {
+            Scale::Linear => Some(v),
+            Scale::Log(base) => (v > 0.).then(|| v.log(base)),
+            Scale::SymLog => Some(v.signum() * v.abs().ln_1p() / std::f64::consts::LN_10),
+        }
+    }
+
+    /// Maps a position along the axis back to the value that is drawn there.
+    pub(crate) fn invert(self, at: f64) -> f64 {
+        let v = match self {
+            Scale::Linear => return at,
+            Scale::Log(base) => base.powf(at),
+            Scale::SymLog => at.signum() * (at.abs() * std::f64::consts::LN_10).exp_m1(),
+        };
+        // going through the logarithm and back leaves some noise (eg, 999.9999999999998)
+        format!("{v:.12e}").parse().expect("formatted f64 parses")
+    }
+
+    /// The positions of whole powers of the base (and of zero, for symlog) between `min` and
+    /// `max` along the axis, with the values they represent.
+    ///
+    /// A linear axis is instead marked every few cells, so it has none.
+    pub(crate) fn ticks(self, min: f64, max: f64) -> Vec<(f64, String)> {
+        let powers = |min: f64, max: f64| {
+            let (first, last) = (min.ceil() as i32, max.floor() as i32);
+            first..=last.max(first - 1)
+        };
+        match self {
+            Scale::Linear => Vec::new(),
+            Scale::Log(base) => powers(min, max)
+                .map(|k| (f64::from(k), power_label(base, k)))
+                .collect(),
+            Scale::SymLog => {
+                let mut ticks = Vec::new();
+                // symlog(±10^k) is only about ±k, so place the ticks where they really land
+                for k in powers(0., max.max(0.)) {
+                    let at = self.apply(10f64.powi(k)).expect("symlog takes anything");
+                    if at <= max {
+                        ticks.push((at, power_label(10., k)));
+                    }
+                }
+                if min <= 0. && max >= 0. {
+                    ticks.push((0., "0".to_string()));
+                }
+                for k in powers(0., (-min).max(0.)) {
+                    let at = self.apply(-10f64.powi(k)).expect("symlog takes anything");
+                    if at >= min {
+                        ticks.push((at, format!("-{}", power_label(10., k))));
+                    }
+                }
+                ticks.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+                ticks
+            }
+        }
+    }
+
+    /// Like [`Scale::ticks`], but for a chart, which (unlike the text plot) has room to label
+    /// round values along a linear axis too.
+    pub(crate) fn chart_ticks(self, min: f64, max: f64) -> Vec<(f64, String)> {
+        if self.is_linear() {
+            linear_ticks(min, max)
+        } else {
+            self.ticks(min, max)
+        }
+    }
+}
+
+/// Evenly spaced ticks at round values between `min` and `max`, for a linear axis.
+fn linear_ticks(min: f64, max: f64) -> Vec<(f64, String)> {
+    // no more than about eight ticks, at a step of 1, 2, or 5 times a power of ten
+    let rough = (max - min) / 8.;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1., 2., 5., 10.]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|&step| step >= rough)
+        .unwrap_or(10. * magnitude);
+    let decimals = (-step.log10().floor()).max(0.) as usize;
+    let (first, last) = ((min / step).ceil() as i64, (max / step).floor() as i64);
+    (first..=last)
+        .map(|k| {
+            let v = k as f64 * step;
+            (v, format!("{v:.decimals$}"))
+        })
+        .collect()
+}
+
+/// Writes `base^k` out in full if that's short, and as `base^k` otherwise.
+fn power_label(base: f64, k: i32) -> String {
+    let v = base.powi(k);
+    let full = format!("{v}");
+    if full.len() <= 6 {
+        full
+    } else if base == 10. {
+        format!("1e{k}")
+    } else {
+        format!("{base}^{k}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_ticks_are_round() {
+        let ticks: Vec<_> = linear_ticks(-0.3, 1.1)
+            .into_iter()
+            .map(|(_, l)| l)
+            .collect();
+        assert_eq!(ticks, ["-0.2", "0.0", "0.2", "0.4", "0.6", "0.8", "1.0"]);
+        let ticks: Vec<_> = linear_ticks(0., 1000.)
+            .into_iter()
+            .map(|(_, l)| l)
+            .collect();
+        assert_eq!(ticks, ["0", "200", "400", "600", "800", "1000"]);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("linear".parse::<Scale>().unwrap(), Scale::Linear);
+        assert_eq!("log".parse::<Scale>().unwrap(), Scale::Log(10.));
+        assert_eq!("log:2".parse::<Scale>().unwrap(), Scale::Log(2.));
+        assert_eq!("symlog".parse::<Scale>().unwrap(), Scale::SymLog);
+        assert_eq!("log:1.5".parse::<Scale>().unwrap(), Scale::Log(1.5));
+        assert!("log:1".parse::<Scale>().is_err());
+        assert!("log:1.0001".parse::<Scale>().is_err());
+        assert!("log:0.5".parse::<Scale>().is_err());
+        assert!("log:inf".parse::<Scale>().is_err());
+        assert!("log:-2".parse::<Scale>().is_err());
+        assert!("cubic".parse::<Scale>().is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        for scale in [
+            Scale::Linear,
+            Scale::Log(10.),
+            Scale::Log(2.),
+            Scale::SymLog,
+        ] {
+            for v in [0.001, 0.5, 1., 3., 1000., 123456.] {
+                let at = scale.apply(v).unwrap();
+                assert_eq!(scale.invert(at), v, "{scale:?} of {v}");
+            }
+        }
+        for v in [-1000., -0.5, 0.] {
+            let at = Scale::SymLog.apply(v).unwrap();
+            assert_eq!(Scale::SymLog.invert(at), v);
+        }
+    }
+
+    #[test]
+    fn non_positive_dropped_by_log() {
+        assert_eq!(Scale::Log(10.).apply(0.), None);
+        assert_eq!(Scale::Log(10.).apply(-1.), None);
+        assert_eq!(Scale::SymLog.apply(0.), Some(0.));
+    }
+
+    #[test]
+    fn ticks() {
+        let labels = |ticks: Vec<(f64, String)>| -> Vec<String> {
+            ticks.into_iter().map(|(_, label)| label).collect()
+        };
+        assert_eq!(
+            labels(Scale::Log(10.).ticks(-1.5, 3.2)),
+            ["0.1", "1", "10", "100", "1000"]
+        );
+        assert_eq!(labels(Scale::Log(10.).ticks(-8., -7.)), ["1e-8", "1e-7"]);
+        assert_eq!(labels(Scale::Log(2.).ticks(0., 3.)), ["1", "2", "4", "8"]);
+        let symlog = Scale::SymLog;
+        let (min, max) = (symlog.apply(-150.).unwrap(), symlog.apply(20.).unwrap());
+        assert_eq!(
+            labels(symlog.ticks(min, max)),
+            ["-100", "-10", "-1", "0", "1", "10"]
+        );
+        assert!(Scale::Linear.ticks(0., 100.).is_empty());
+    }
+}
@@ -0,0 +1,32 @@
+/// Rows of the terminal that are not available to the canvas: the header line printed above the
+/// canvas, the blank line printed after it, and the shell prompt that follows.
+const RESERVED_ROWS: usize = 3;
+
+/// The dimensions used if the size of the terminal cannot be determined.
+const DEFAULT_WIDTH: usize = 72;
+const DEFAULT_HEIGHT: usize = 40;
+
+/// Returns the (width, height) of the canvas that would fill the terminal.
+///
+/// The size is taken from the terminal attached to stdout if there is one, and from `$COLUMNS`
+/// and `$LINES` otherwise. Each dimension for which neither is available falls back to the
+/// fixed default.
+pub(crate) fn canvas_size() -> (usize, usize) {
+    let (columns, lines) = match terminal_size::terminal_size_of(std::io::stdout()) {
+        Some((terminal_size::Width(w), terminal_size::Height(h))) => {
+            (Some(usize::from(w)), Some(usize::from(h)))
+        }
+        None => (env_usize("COLUMNS"), env_usize("LINES")),
+    };
+
+    let width = columns.filter(|&w| w > crate::PAD).unwrap_or(DEFAULT_WIDTH);
+    let height = lines
+        .and_then(|h| h.checked_sub(RESERVED_ROWS))
+        .filter(|&h| h > crate::PAD)
+        .unwrap_or(DEFAULT_HEIGHT);
+    (width, height)
+}
+
+fn env_usize(var: &str) -> Option<usize> {
+    std::env::var(var).ok()?.trim().parse().ok()
+}
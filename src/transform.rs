@@ -0,0 +1,605 @@
+use crate::scale::Scale;
+use crate::{Data, MARKS, PAD};
+use hdrhistogram::Histogram;
+use std::collections::{BTreeMap, VecDeque};
+use std::str::FromStr;
+
+/// A smoothing transform applied to each Y column before plotting.
+///
+/// All of these are trailing, so the smoothed value at a given row only depends on that row and
+/// the rows before it. Missing (NaN) values are skipped: they stay missing, and do not take up a
+/// slot in the window.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Smooth {
+    /// The mean of the last N values.
+    Mean(usize),
+    /// The median of the last N values.
+    Median(usize),
+    /// An exponentially weighted moving average where each new value has weight ALPHA.
+    Ewma(f64),
+}
+
+impl FromStr for Smooth {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((kind, param)) = s.split_once(':') else {
+            eyre::bail!("expected KIND:PARAM (eg, mean:10), got '{s}'");
+        };
+        match kind {
+            "mean" | "median" => {
+                let n: usize = param
+                    .parse()
+                    .map_err(|e| eyre::eyre!("invalid window size '{param}': {e}"))?;
+                eyre::ensure!(n > 0, "window size must be at least 1");
+                Ok(if kind == "mean" {
+                    Smooth::Mean(n)
+                } else {
+                    Smooth::Median(n)
+                })
+            }
+            "ewma" => {
+                let alpha: f64 = param
+                    .parse()
+                    .map_err(|e| eyre::eyre!("invalid alpha '{param}': {e}"))?;
+                eyre::ensure!(
+                    alpha > 0. && alpha <= 1.,
+                    "alpha must be in (0, 1], got {alpha}"
+                );
+                Ok(Smooth::Ewma(alpha))
+            }
+            _ => eyre::bail!("unknown smoothing '{kind}'; expected mean, median, or ewma"),
+        }
+    }
+}
+
+impl Smooth {
+    pub(crate) fn apply(self, ys: &mut [f64]) {
+        match self {
+            Smooth::Mean(n) => windowed(ys, n, |window| {
+                window.iter().sum::<f64>() / window.len() as f64
+            }),
+            Smooth::Median(n) => {
+                let mut sorted = Vec::with_capacity(n);
+                windowed(ys, n, |window| {
+                    sorted.clear();
+                    sorted.extend(window);
+                    sorted.sort_by(f64::total_cmp);
+                    let mid = sorted.len() / 2;
+                    if sorted.len() % 2 == 0 {
+                        (sorted[mid - 1] + sorted[mid]) / 2.
+                    } else {
+                        sorted[mid]
+                    }
+                })
+            }
+            Smooth::Ewma(alpha) => {
+                let mut average = None;
+                for y in ys.iter_mut().filter(|y| y.is_finite()) {
+                    let next = match average {
+                        None => *y,
+                        Some(average) => alpha * *y + (1. - alpha) * average,
+                    };
+                    average = Some(next);
+                    *y = next;
+                }
+            }
+        }
+    }
+}
+
+/// Replaces each finite value in `ys` with `f` of the (up to) `n` finite values ending with it.
+fn windowed(ys: &mut [f64], n: usize, mut f: impl FnMut(&VecDeque<f64>) -> f64) {
+    let mut window = VecDeque::with_capacity(n);
+    for y in ys.iter_mut().filter(|y| y.is_finite()) {
+        if window.len() == n {
+            window.pop_front();
+        }
+        window.push_back(*y);
+        *y = f(&window);
+    }
+}
+
+/// A transform between the values of a Y column and how much they change from row to row.
+///
+/// Missing (NaN) values are skipped, so a change is always relative to the closest preceding row
+/// that _did_ have a value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Delta {
+    /// The change in Y per unit of X, for monotonic counters.
+    ///
+    /// A decrease is taken to mean that the counter was reset, and is treated as missing, as are
+    /// rows whose X does not increase.
+    Rate,
+    /// The plain difference between successive values.
+    Diff,
+    /// The running sum of all values so far (the inverse of [`Delta::Diff`]).
+    CumSum,
+}
+
+impl Delta {
+    pub(crate) fn apply(self, xs: &[f64], ys: &mut [f64]) {
+        match self {
+            Delta::Rate | Delta::Diff => {
+                let mut previous: Option<(f64, f64)> = None;
+                for (&x, y) in xs.iter().zip(ys.iter_mut()) {
+                    if !y.is_finite() {
+                        continue;
+                    }
+                    let current = *y;
+                    *y = match previous {
+                        None => f64::NAN,
+                        Some((_, py)) if self == Delta::Diff => current - py,
+                        Some((px, py)) => {
+                            let (dx, dy) = (x - px, current - py);
+                            if dy < 0. || dx <= 0. {
+                                f64::NAN
+                            } else {
+                                dy / dx
+                            }
+                        }
+                    };
+                    previous = Some((x, current));
+                }
+            }
+            Delta::CumSum => {
+                let mut sum = 0.;
+                for y in ys.iter_mut().filter(|y| y.is_finite()) {
+                    sum += *y;
+                    *y = sum;
+                }
+            }
+        }
+    }
+}
+
+/// Replaces each Y column with a set of quantiles over buckets of X values.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Quantiles {
+    /// The quantiles to compute, as percentiles (so 99 means p99).
+    pub(crate) percentiles: Vec<f64>,
+    /// The width of each bucket along the X axis.
+    ///
+    /// If not given, each bucket is as wide as one column of the plot.
+    pub(crate) bucket: Option<f64>,
+}
+
+impl Quantiles {
+    /// Computes the quantiles of `data`, plotted over a canvas `width` wide.
+    ///
+    /// Each bucket covers X values in `[n * bucket, (n + 1) * bucket)`, and is plotted at the
+    /// start of that range. Buckets without any values are left out.
+    pub(crate) fn apply(&self, data: &Data, width: usize) -> eyre::Result<Data> {
+        let finite_xs = || data.xs.iter().copied().filter(|x| x.is_finite());
+        let (Some(min_x), Some(max_x)) = (
+            finite_xs().min_by(f64::total_cmp),
+            finite_xs().max_by(f64::total_cmp),
+        ) else {
+            return Ok(Data::default());
+        };
+        let bucket = self
+            .bucket
+            .unwrap_or_else(|| (max_x - min_x) / (width - PAD) as f64);
+        // all X values are the same, so they all go in one bucket
+        let bucket = if bucket > 0. { bucket } else { 1. };
+
+        let scale = HistogramScale::over(data);
+        let mut buckets: BTreeMap<i64, Vec<Histogram<u64>>> = BTreeMap::new();
+        for (row, &x) in data.xs.iter().enumerate() {
+            if !x.is_finite() {
+                continue;
+            }
+            let histograms = buckets
+                .entry((x / bucket).floor() as i64)
+                .or_insert_with(|| vec![scale.histogram(); data.ys.len()]);
+            for (histogram, ys) in histograms.iter_mut().zip(&data.ys) {
+                scale.record(histogram, ys[row]);
+            }
+        }
+
+        let labels: Vec<_> = (0..data.ys.len()).map(|c| data.label(c)).collect();
+        let buckets = buckets
+            .into_iter()
+            .map(|(n, histograms)| (n as f64 * bucket, histograms));
+        let quantiles = self.of_histograms(&labels, buckets, |v| scale.value(v))?;
+        Ok(Data {
+            x_unit: data.x_unit.clone(),
+            y_unit: data.y_unit.clone(),
+            ..quantiles
+        })
+    }
+
+    /// Computes the quantiles of each histogram in `buckets`, which hold one histogram per column
+    /// (named by `labels`) for each X value.
+    ///
+    /// `value` maps what is recorded in the histograms back to the values to plot.
+    pub(crate) fn of_histograms(
+        &self,
+        labels: &[String],
+        buckets: impl Iterator<Item = (f64, Vec<Histogram<u64>>)>,
+        value: impl Fn(u64) -> f64,
+    ) -> eyre::Result<Data> {
+        let series = labels.len() * self.percentiles.len();
+        eyre::ensure!(
+            series <= MARKS.len(),
+            "{} quantiles of {} columns makes {series} series, but only {} can be plotted",
+            self.percentiles.len(),
+            labels.len(),
+            MARKS.len()
+        );
+
+        let mut quantiles = Data {
+            ys: vec![Vec::new(); series],
+            labels: Vec::with_capacity(series),
+            ..Data::default()
+        };
+        for label in labels {
+            for p in &self.percentiles {
+                quantiles.labels.push(if labels.len() == 1 {
+                    format!("p{p}")
+                } else {
+                    format!("{label} p{p}")
+                });
+            }
+        }
+
+        for (x, histograms) in buckets {
+            quantiles.xs.push(x);
+            let mut series = quantiles.ys.iter_mut();
+            for histogram in &histograms {
+                for p in &self.percentiles {
+                    let ys = series.next().expect("one series per column per quantile");
+                    ys.push(if histogram.is_empty() {
+                        f64::NAN
+                    } else {
+                        value(histogram.value_at_percentile(*p))
+                    });
+                }
+            }
+        }
+        Ok(quantiles)
+    }
+}
+
+/// Replaces each Y column with its exact empirical cumulative distribution, for plotting on a
+/// canvas of the given size whose axes have the given scales.
+///
+/// The values end up along the X axis, and the percentage of values at most (or, if
+/// `complementary`, greater than) each X along the Y axis. The distribution is a step function, so
+/// it is sampled once per column of the plot to draw the flat parts, and drawn at each value to
+/// draw the jumps. Jumps that fall in the same cell of the plot are only drawn once, so there are
+/// only so many points however many values there are.
+pub(crate) fn cdf(
+    data: &Data,
+    (width, height): (usize, usize),
+    complementary: bool,
+    (x_scale, y_scale): (Scale, Scale),
+) -> Data {
+    let sorted: Vec<Vec<f64>> = data
+        .ys
+        .iter()
+        .map(|ys| {
+            let mut values: Vec<_> = ys.iter().copied().filter(|y| y.is_finite()).collect();
+            values.sort_by(f64::total_cmp);
+            values
+        })
+        .collect();
+
+    let mut cdf = Data {
+        ys: vec![Vec::new(); data.ys.len()],
+        x_unit: data.y_unit.clone(),
+        labels: data.labels.clone(),
+        ..Data::default()
+    };
+    // the flat parts are sampled evenly along the axis as drawn
+    let along_axis = || sorted.iter().flatten().filter_map(|&v| x_scale.apply(v));
+    let (Some(min), Some(max)) = (
+        along_axis().min_by(f64::total_cmp),
+        along_axis().max_by(f64::total_cmp),
+    ) else {
+        return cdf;
+    };
+
+    let (plot_width, plot_height) = (width - PAD, height - PAD);
+    let percent = |values: &[f64], n: usize| {
+        let p = 100. * n as f64 / values.len() as f64;
+        if complementary {
+            100. - p
+        } else {
+            p
+        }
+    };
+    let at_most = |values: &[f64], x: f64| percent(values, values.partition_point(|&v| v <= x));
+    let below = |values: &[f64], x: f64| percent(values, values.partition_point(|&v| v < x));
+
+    // every column is sampled at the same X, so they can share rows
+    for c in 0..=plot_width {
+        let x = x_scale.invert(min + (max - min) * c as f64 / plot_width as f64);
+        cdf.xs.push(x);
+        for (ys, values) in cdf.ys.iter_mut().zip(&sorted) {
+            ys.push(if values.is_empty() {
+                f64::NAN
+            } else {
+                at_most(values, x)
+            });
+        }
+    }
+
+    // which cell of the plot a point falls in (roughly, since the frame isn't known yet), if it
+    // can be drawn at all. the smallest percentage that isn't 0 is that of one of the most values.
+    let cell_of = |(min, max): (f64, f64), v: f64, cells: usize| {
+        if max > min {
+            ((v - min) / (max - min) * cells as f64).round() as usize
+        } else {
+            0
+        }
+    };
+    let most = sorted.iter().map(Vec::len).max().unwrap_or(1);
+    let percents = [0., 100. / most as f64, 100.].into_iter();
+    let percents = percents.filter_map(|p| y_scale.apply(p));
+    let y_range = (
+        percents.clone().fold(f64::INFINITY, f64::min),
+        percents.fold(f64::NEG_INFINITY, f64::max),
+    );
+    let column_of = |v: f64| x_scale.apply(v).map(|v| cell_of((min, max), v, plot_width));
+    let row_of = |p: f64| y_scale.apply(p).map(|p| cell_of(y_range, p, plot_height));
+
+    for (column, values) in sorted.iter().enumerate() {
+        let mut distinct = values.clone();
+        distinct.dedup();
+        for jumps in distinct.chunk_by(|&a, &b| column_of(a) == column_of(b)) {
+            let points: Vec<_> = jumps
+                .iter()
+                .flat_map(|&v| [(v, below(values, v)), (v, at_most(values, v))])
+                .collect();
+            // the first and last points of the jump are always drawn, so it spans the right rows
+            let mut last_row = None;
+            for (i, &(x, p)) in points.iter().enumerate() {
+                let row = row_of(p);
+                if i != 0 && i != points.len() - 1 && row == last_row {
+                    continue;
+                }
+                last_row = row;
+                cdf.xs.push(x);
+                for (c, ys) in cdf.ys.iter_mut().enumerate() {
+                    ys.push(if c == column { p } else { f64::NAN });
+                }
+            }
+        }
+    }
+    cdf
+}
+
+/// Plots the distribution of each Y column with the percentile along the X axis and the value
+/// at that percentile along the Y axis, like HdrHistogram's plotter.
+///
+/// The X axis is `log10(1 / (1 - p))` for a percentile `p` (as a fraction), so that 90%, 99%,
+/// 99.9%, and so on are evenly spaced, and the tail gets as much room as the median. See
+/// [`percentile_of_x`] for going back the other way.
+pub(crate) fn percentile_plot(data: &Data) -> Data {
+    let scale = HistogramScale::over(data);
+    let histograms: Vec<_> = data
+        .ys
+        .iter()
+        .map(|ys| {
+            let mut histogram = scale.histogram();
+            for &y in ys {
+                scale.record(&mut histogram, y);
+            }
+            histogram
+        })
+        .collect();
+    Data {
+        y_unit: data.y_unit.clone(),
+        labels: data.labels.clone(),
+        ..percentile_plot_of(&histograms, |v| scale.value(v))
+    }
+}
+
+/// Like [`percentile_plot`], but of the distribution in each of `histograms`.
+///
+/// `value` maps what is recorded in the histograms back to the values to plot.
+pub(crate) fn percentile_plot_of(
+    histograms: &[Histogram<u64>],
+    value: impl Fn(u64) -> f64,
+) -> Data {
+    /// How many points to compute per halving of the distance to 100%.
+    const TICKS_PER_HALF_DISTANCE: u32 = 5;
+
+    let mut plot = Data {
+        ys: vec![Vec::new(); histograms.len()],
+        ..Data::default()
+    };
+    for (column, histogram) in histograms.iter().enumerate() {
+        for v in histogram.iter_quantiles(TICKS_PER_HALF_DISTANCE) {
+            let q = v.quantile_iterated_to();
+            if q >= 1. {
+                // 1 / (1 - q) is infinite, so this can't be plotted
+                continue;
+            }
+            plot.xs.push((1. / (1. - q)).log10());
+            for (c, ys) in plot.ys.iter_mut().enumerate() {
+                ys.push(if c == column {
+                    // the middle of the histogram bucket is the best guess at the real value
+                    value(histogram.median_equivalent(v.value_iterated_to()))
+                } else {
+                    f64::NAN
+                });
+            }
+        }
+    }
+    plot
+}
+
+/// Turns an X value from [`percentile_plot`] back into the percentile it represents.
+pub(crate) fn percentile_of_x(x: f64) -> f64 {
+    100. * (1. - 10f64.powf(-x))
+}
+
+/// Maps the (finite) Y values of a data set onto the positive integers a [`Histogram`] holds.
+///
+/// The values are shifted and scaled to give the largest one 2^40, which leaves plenty of room for
+/// the 3 significant figures the histogram keeps track of.
+#[derive(Debug, Clone, Copy)]
+struct HistogramScale {
+    min: f64,
+    scale: f64,
+}
+
+impl HistogramScale {
+    fn over(data: &Data) -> Self {
+        let finite_ys = || data.ys.iter().flatten().copied().filter(|y| y.is_finite());
+        let min = finite_ys().min_by(f64::total_cmp).unwrap_or(0.);
+        let max = finite_ys().max_by(f64::total_cmp).unwrap_or(0.);
+        let scale = if max > min {
+            (1u64 << 40) as f64 / (max - min)
+        } else {
+            1.
+        };
+        Self { min, scale }
+    }
+
+    fn histogram(&self) -> Histogram<u64> {
+        Histogram::new(3).expect("3 is a valid sigfig")
+    }
+
+    /// Records `y` into `histogram`, unless it is missing.
+    fn record(&self, histogram: &mut Histogram<u64>, y: f64) {
+        if y.is_finite() {
+            histogram
+                .record(((y - self.min) * self.scale).round() as u64)
+                .expect("histogram auto-resizes");
+        }
+    }
+
+    /// Maps a value from the histogram back to the original scale.
+    fn value(&self, v: u64) -> f64 {
+        v as f64 / self.scale + self.min
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The points of Y column `column` of `data` that aren't missing.
+    fn points(data: &Data, column: usize) -> Vec<(f64, f64)> {
+        data.xs
+            .iter()
+            .zip(&data.ys[column])
+            .filter(|(_, y)| !y.is_nan())
+            .map(|(&x, &y)| (x, y))
+            .collect()
+    }
+
+    /// Whether `a` and `b` are the same, where NaN is the same as NaN.
+    fn same(a: &[f64], b: &[f64]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.total_cmp(b).is_eq())
+    }
+
+    #[test]
+    fn smooth() {
+        let nan = f64::NAN;
+        for (smooth, ys, expected) in [
+            (Smooth::Mean(2), [1., nan, 3., 5.], [1., nan, 2., 4.]),
+            (Smooth::Median(3), [1., 9., nan, 2.], [1., 5., nan, 2.]),
+            (Smooth::Ewma(0.5), [2., 4., nan, 7.], [2., 3., nan, 5.]),
+        ] {
+            let mut smoothed = ys;
+            smooth.apply(&mut smoothed);
+            assert!(same(&smoothed, &expected), "{smooth:?}: {smoothed:?}");
+        }
+    }
+
+    #[test]
+    fn delta() {
+        let nan = f64::NAN;
+        let xs = [0., 1., 2., 3., 4.];
+        for (delta, ys, expected) in [
+            // the reset from 30 to 5 is missing, not negative
+            (
+                Delta::Rate,
+                [0., 10., nan, 30., 5.],
+                [nan, 10., nan, 10., nan],
+            ),
+            (Delta::Diff, [1., 4., nan, 2., 2.], [nan, 3., nan, -2., 0.]),
+            (Delta::CumSum, [1., nan, 2., 3., -6.], [1., nan, 3., 6., 0.]),
+        ] {
+            let mut changed = ys;
+            delta.apply(&xs, &mut changed);
+            assert!(same(&changed, &expected), "{delta:?}: {changed:?}");
+        }
+    }
+
+    #[test]
+    fn quantiles() {
+        let data = Data {
+            xs: (0..200).map(f64::from).collect(),
+            ys: vec![(0..200).map(|y| f64::from(y % 100 + 1)).collect()],
+            ..Data::default()
+        };
+        let quantiles = Quantiles {
+            percentiles: vec![50., 100.],
+            bucket: Some(100.),
+        };
+        let quantiles = quantiles.apply(&data, 80).unwrap();
+        assert_eq!(quantiles.labels, ["p50", "p100"]);
+        assert_eq!(quantiles.xs, [0., 100.]);
+        for ys in &quantiles.ys {
+            assert_eq!(ys[0], ys[1]);
+        }
+        assert!((quantiles.ys[0][0] - 50.).abs() <= 1., "{:?}", quantiles.ys);
+        assert!(
+            (quantiles.ys[1][0] - 100.).abs() <= 1.,
+            "{:?}",
+            quantiles.ys
+        );
+    }
+
+    #[test]
+    fn cdf_steps_at_ties() {
+        let data = Data {
+            xs: vec![0.; 5],
+            ys: vec![vec![1., 2., 2., 3., f64::NAN], vec![f64::NAN; 5]],
+            ..Data::default()
+        };
+        let scales = (Scale::Linear, Scale::Linear);
+        let cdf = cdf(&data, (80, 40), false, scales);
+        let points = points(&cdf, 0);
+        // both ends of the jump at 2, which has two of the four values
+        assert!(points.contains(&(2., 25.)), "{points:?}");
+        assert!(points.contains(&(2., 75.)), "{points:?}");
+        assert!(points.contains(&(3., 100.)), "{points:?}");
+        // and the flat part between 2 and 3
+        let mut flat = points.iter().filter(|&&(x, _)| x > 2. && x < 3.);
+        assert!(flat.clone().count() > 1 && flat.all(|&(_, p)| p == 75.));
+        assert!(self::points(&cdf, 1).is_empty());
+
+        let ccdf = super::cdf(&data, (80, 40), true, scales);
+        let points = self::points(&ccdf, 0);
+        assert!(points.contains(&(1., 100.)), "{points:?}");
+        assert!(points.contains(&(3., 0.)), "{points:?}");
+        // nothing is greater than the largest value, but a quarter of the values are at it
+        assert!(points.contains(&(3., 25.)), "{points:?}");
+    }
+
+    #[test]
+    fn cdf_is_bounded_by_the_canvas() {
+        let (width, height) = (80, 40);
+        let data = Data {
+            xs: vec![0.; 100_000],
+            ys: vec![(0..100_000).map(f64::from).collect(); 3],
+            ..Data::default()
+        };
+        for scale in [Scale::Linear, Scale::Log(10.)] {
+            let cdf = cdf(&data, (width, height), false, (scale, scale));
+            // a flat part and the two ends of a jump in each column, and a point per row
+            assert!(cdf.xs.len() <= 3 * (3 * width + height), "{}", cdf.xs.len());
+            for column in 0..3 {
+                let points = points(&cdf, column);
+                assert!(points.contains(&(0., 0.)), "{scale:?}");
+                assert!(points.contains(&(99_999., 100.)), "{scale:?}");
+            }
+        }
+    }
+}
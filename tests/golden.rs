@@ -0,0 +1,323 @@
+//! Golden-output tests that run dings over the inputs in `tests/fixtures/` and compare what it
+//! prints to the snapshots in `tests/snapshots/`.
+//!
+//! Run with `DINGS_BLESS=1` to (re)write the snapshots from the current output instead.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+fn golden(name: &str, fixture: &str, args: &[&str]) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let input = std::fs::read(root.join("fixtures").join(fixture)).expect("read fixture");
+
+    // the fixture config (rather than that of whoever runs the tests) only has presets, so it
+    // only affects the tests that name them
+    let config = root.join("fixtures").join("config.toml");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dings"))
+        .args(args)
+        .env("DINGS_CONFIG", config)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn dings");
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(&input)
+        .expect("write fixture to dings");
+    let output = child.wait_with_output().expect("wait for dings");
+    assert!(
+        output.status.success(),
+        "dings {args:?} < {fixture} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let actual = String::from_utf8(output.stdout).expect("output is utf-8");
+
+    let snapshot = root.join("snapshots").join(format!("{name}.txt"));
+    if std::env::var_os("DINGS_BLESS").is_some() {
+        std::fs::write(&snapshot, &actual).expect("write snapshot");
+        return;
+    }
+    let expected = std::fs::read_to_string(&snapshot)
+        .unwrap_or_else(|e| panic!("read snapshot {}: {e}", snapshot.display()));
+    assert!(
+        actual == expected,
+        "output of dings {args:?} < {fixture} does not match {}\n\
+         (run with DINGS_BLESS=1 to update it)\n\
+         --- expected ---\n{expected}\n--- actual ---\n{actual}",
+        snapshot.display()
+    );
+}
+
+#[test]
+fn dot() {
+    golden("dot", "waves.txt", &["-d", "60x20"]);
+}
+
+#[test]
+fn count() {
+    golden("count", "waves.txt", &["-d", "60x20", "-m", "count"]);
+}
+
+#[test]
+fn no_axes() {
+    golden("no-axes", "waves.txt", &["-d", "60x20", "-A"]);
+}
+
+#[test]
+fn explicit_x() {
+    golden("explicit-x", "xy.txt", &["-d", "60x20", "-x"]);
+}
+
+#[test]
+fn log_x() {
+    golden("log-x", "xy.txt", &["-d", "60x20", "-x", "-l", "x"]);
+}
+
+#[test]
+fn log_y() {
+    golden("log-y", "xy.txt", &["-d", "60x20", "-x", "-l", "y"]);
+}
+
+#[test]
+fn log_xy_count() {
+    let args = ["-d", "60x20", "-x", "-l", "x", "-l", "y", "-m", "count"];
+    golden("log-xy-count", "xy.txt", &args);
+}
+
+#[test]
+fn cdf() {
+    golden("cdf", "latency.txt", &["-d", "60x20", "--cdf"]);
+}
+
+#[test]
+fn cdf_log_y() {
+    let args = ["-d", "60x20", "--cdf", "-l", "y"];
+    golden("cdf-log-y", "latency.txt", &args);
+}
+
+#[test]
+fn grid() {
+    golden("grid", "xy.txt", &["-d", "80x24", "-x", "--grid", "1x2"]);
+}
+
+#[test]
+fn facet_independent_axes() {
+    let args = ["-d", "80x24", "-x", "--facet", "--independent-axes"];
+    golden("facet-independent-axes", "xy.txt", &args);
+}
+
+#[test]
+fn smooth_show_raw() {
+    let args = ["-d", "60x20", "--smooth", "mean:8", "--show-raw"];
+    golden("smooth-show-raw", "waves.txt", &args);
+}
+
+#[test]
+fn rate() {
+    golden("rate", "counter.txt", &["-d", "60x20", "-x", "--rate"]);
+}
+
+#[test]
+fn reduce() {
+    golden("reduce", "waves.txt", &["-d", "60x20", "--reduce"]);
+}
+
+#[test]
+fn reduce_count() {
+    let args = ["-d", "60x20", "--reduce", "-m", "count"];
+    golden("reduce-count", "waves.txt", &args);
+}
+
+#[test]
+fn units() {
+    golden("units", "units.txt", &["-d", "60x20", "-x", "--units"]);
+}
+
+#[test]
+fn quantiles() {
+    let args = ["-d", "60x20", "--quantiles", "50,90,99", "--bucket", "20"];
+    golden("quantiles", "latency.txt", &args);
+}
+
+#[test]
+fn percentile_plot() {
+    let args = ["-d", "60x20", "--percentile-plot"];
+    golden("percentile-plot", "latency.txt", &args);
+}
+
+#[test]
+fn hlog() {
+    golden("hlog", "latency.hlog", &["-d", "70x20", "--format", "hlog"]);
+}
+
+#[test]
+fn hlog_quantiles() {
+    let args = ["-d", "70x20", "--format", "hlog", "--quantiles", "50,99"];
+    golden("hlog-quantiles", "latency.hlog", &args);
+}
+
+#[test]
+fn ccdf_log_xy() {
+    let args = ["-d", "60x20", "--ccdf", "-l", "x", "-l", "y"];
+    golden("ccdf-log-xy", "latency.txt", &args);
+}
+
+#[test]
+fn symlog_y() {
+    golden(
+        "symlog-y",
+        "waves.txt",
+        &["-d", "60x20", "--y-scale", "symlog"],
+    );
+}
+
+#[test]
+fn log2_y_dropped() {
+    let args = ["-d", "60x20", "-x", "--y-scale", "log:2", "--diff"];
+    golden("log2-y-dropped", "xy.txt", &args);
+}
+
+#[test]
+fn fit_poly() {
+    golden(
+        "fit-poly",
+        "xy.txt",
+        &["-d", "60x20", "-x", "--fit", "poly:2"],
+    );
+}
+
+#[test]
+fn fit_linear_count() {
+    let args = ["-d", "60x20", "-x", "--fit", "linear", "-m", "count"];
+    golden("fit-linear-count", "counter.txt", &args);
+}
+
+#[test]
+fn reference_lines() {
+    let args = [
+        "-d",
+        "60x20",
+        "--hline",
+        "250:SLO",
+        "--vline",
+        "40:deploy",
+        "--band",
+        "100:150:ok",
+    ];
+    golden("reference-lines", "latency.txt", &args);
+}
+
+#[test]
+fn reference_lines_count() {
+    let args = [
+        "-d", "60x20", "-m", "count", "--hline", "-5:low", "--band", "2:4",
+    ];
+    golden("reference-lines-count", "waves.txt", &args);
+}
+
+#[test]
+fn outliers() {
+    let args = ["-d", "60x20", "--outliers", "iqr", "--top-outliers", "3"];
+    golden("outliers", "latency.txt", &args);
+}
+
+#[test]
+fn outliers_grid_count() {
+    let args = [
+        "-d",
+        "60x20",
+        "-x",
+        "--facet",
+        "-m",
+        "count",
+        "--outliers",
+        "zscore:2",
+    ];
+    golden("outliers-grid-count", "xy.txt", &args);
+}
+
+#[test]
+fn preset() {
+    golden("preset", "latency.txt", &["@latency", "-d", "60x20"]);
+}
+
+#[test]
+fn preset_turned_off() {
+    let args = ["@latency", "--no-ccdf", "-d", "60x20"];
+    golden("preset-turned-off", "latency.txt", &args);
+}
+
+#[test]
+fn emit_json_ccdf() {
+    let args = [
+        "-d", "30x10", "--ccdf", "-l", "x", "-l", "y", "--emit", "json",
+    ];
+    golden("emit-json-ccdf", "units.txt", &args);
+}
+
+#[test]
+fn emit_tsv_count() {
+    let args = ["-d", "20x8", "-x", "-m", "count", "--emit", "tsv"];
+    golden("emit-tsv-count", "xy.txt", &args);
+}
+
+#[test]
+fn html() {
+    let args = ["-d", "40x12", "-x", "-l", "y", "--output", "html"];
+    golden("html", "xy.txt", &args);
+}
+
+#[test]
+fn html_annotations() {
+    let args = [
+        "-d",
+        "40x12",
+        "--smooth",
+        "mean:5",
+        "--show-raw",
+        "--hline",
+        "5:SLO",
+        "--vline",
+        "30:deploy",
+        "--band",
+        "-2:-1:quiet",
+        "--output",
+        "html",
+    ];
+    golden("html-annotations", "waves.txt", &args);
+}
+
+#[test]
+fn html_unicode() {
+    let args = ["-d", "40x12", "--charset", "unicode", "--output", "html"];
+    golden("html-unicode", "latency.txt", &args);
+}
+
+#[test]
+fn inline_image_falls_back_to_text() {
+    // stdout is not a terminal, so there's none to show an image in
+    let args = ["-d", "60x20", "--output", "kitty"];
+    golden("dot", "waves.txt", &args);
+}
+
+#[test]
+fn unicode_halves() {
+    let args = ["-d", "60x20", "--charset", "unicode"];
+    golden("unicode-halves", "latency.txt", &args);
+}
+
+#[test]
+fn unicode_count() {
+    let args = ["-d", "60x20", "-m", "count", "--charset", "unicode"];
+    golden("unicode-count", "waves.txt", &args);
+}
+
+#[test]
+fn unicode_grid() {
+    let args = ["-d", "80x24", "-x", "--grid", "1x2", "--charset", "unicode"];
+    golden("unicode-grid", "xy.txt", &args);
+}